@@ -0,0 +1,240 @@
+
+use image::ImageEncoder;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::shares::ShareOutcome;
+use crate::stats::Stats;
+use crate::Error;
+
+
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 700;
+
+const MOST_WANTED: usize = 20;
+
+/// how far back the share outcomes chart goes
+const HISTORY_DAYS: u64 = 30;
+
+const DAY: u64 = 60 * 60 * 24;
+
+/// the font is built in so charts render the same everywhere, without needing fonts installed
+const FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+static REGISTER_FONT: std::sync::Once = std::sync::Once::new();
+
+
+/// renders every chart as png files, with their file names
+pub fn render_all(stats: &Stats) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    Ok(vec![
+        ("needs_by_category.png".to_string(), needs_by_category(stats)?),
+        ("share_outcomes.png".to_string(), share_outcomes(stats)?),
+        ("most_wanted.png".to_string(), most_wanted(stats)?),
+    ])
+}
+
+
+/// draws onto a blank bitmap and encodes it as a png
+fn render(draw: impl FnOnce(&DrawingArea<BitMapBackend, Shift>) -> Result<(), Error>) -> Result<Vec<u8>, Error> {
+    REGISTER_FONT.call_once(|| {
+        plotters::style::register_font("sans-serif", FontStyle::Normal, FONT).map_err(|_| ()).expect("the built in font is invalid");
+    });
+
+    let mut buffer = vec![0; (WIDTH * HEIGHT * 3) as usize];
+
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+        draw(&root)?;
+        root.present()?;
+    }
+
+    let mut png = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png).write_image(&buffer, WIDTH, HEIGHT, image::ColorType::Rgb8)?;
+
+    Ok(png)
+}
+
+
+pub fn needs_by_category(stats: &Stats) -> Result<Vec<u8>, Error> {
+
+    let names = Vec::from_iter(stats.needs_by_category.iter().map(|(category, _)| category.to_string()));
+    let totals = Vec::from_iter(stats.needs_by_category.iter().map(|(_, counts)| counts.iter().map(|(_, count)| count).sum::<usize>()));
+    let max = totals.iter().copied().max().unwrap_or(0).max(1);
+
+    render(|root| {
+        let mut chart = ChartBuilder::on(root)
+            .caption("Needs by category", ("sans-serif", 40))
+            .margin(20)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d((0..names.len() - 1).into_segmented(), 0..max + max / 10 + 1)?;
+
+        chart.configure_mesh()
+            .disable_x_mesh()
+            .x_label_style(("sans-serif", 20))
+            .y_label_style(("sans-serif", 20))
+            .x_label_formatter(&|value| match value {
+                SegmentValue::CenterOf(i) => names.get(*i).cloned().unwrap_or_default(),
+                _ => String::new(),
+            })
+            .draw()?;
+
+        chart.draw_series(totals.iter().enumerate().map(|(i, &total)| {
+            let mut bar = Rectangle::new([(SegmentValue::Exact(i), 0), (SegmentValue::Exact(i + 1), total)], BLUE.mix(0.7).filled());
+            bar.set_margin(0, 0, 20, 20);
+            bar
+        }))?;
+
+        Ok(())
+    })
+}
+
+
+pub fn most_wanted(stats: &Stats) -> Result<Vec<u8>, Error> {
+
+    let mut counts = Vec::from_iter(stats.needs_by_category.iter().flat_map(|(_, counts)| counts.iter()));
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+    counts.truncate(MOST_WANTED);
+
+    let max = counts.first().map(|(_, count)| *count).unwrap_or(0).max(1);
+
+    // always leave room for the full top list so the bars are the same size however many there are
+    let rows = MOST_WANTED;
+
+    render(|root| {
+        let mut chart = ChartBuilder::on(root)
+            .caption(format!("Top {} most wanted", MOST_WANTED), ("sans-serif", 40))
+            .margin(20)
+            .x_label_area_size(40)
+            .y_label_area_size(320)
+            .build_cartesian_2d(0..max + max / 10 + 1, (0..rows - 1).into_segmented())?;
+
+        // the most wanted goes at the top
+        chart.configure_mesh()
+            .disable_y_mesh()
+            .x_label_style(("sans-serif", 20))
+            .y_label_style(("sans-serif", 18))
+            .y_labels(rows)
+            .y_label_formatter(&|value| match value {
+                SegmentValue::CenterOf(i) => rows.checked_sub(i + 1).and_then(|row| counts.get(row)).map(|(cosmetic, _)| cosmetic.clone()).unwrap_or_default(),
+                _ => String::new(),
+            })
+            .draw()?;
+
+        chart.draw_series(counts.iter().enumerate().map(|(i, (_, count))| {
+            let row = rows - 1 - i;
+            let mut bar = Rectangle::new([(0, SegmentValue::Exact(row)), (*count, SegmentValue::Exact(row + 1))], BLUE.mix(0.7).filled());
+            bar.set_margin(4, 4, 0, 0);
+            bar
+        }))?;
+
+        Ok(())
+    })
+}
+
+
+pub fn share_outcomes(stats: &Stats) -> Result<Vec<u8>, Error> {
+
+    let today = crate::now() / DAY;
+
+    // how many copies ended each way on each day, oldest day first
+    let per_day = |outcome: ShareOutcome| -> Vec<(i64, usize)> {
+        Vec::from_iter((0..HISTORY_DAYS).rev().map(|days_ago| {
+            let count = stats.share_history.iter()
+                .filter(|(created_at, share_outcome)| *share_outcome == outcome && today.saturating_sub(created_at / DAY) == days_ago)
+                .count();
+
+            (-(days_ago as i64), count)
+        }))
+    };
+
+    let outcomes = [
+        ("Claimed", ShareOutcome::Claimed, GREEN),
+        ("Timed out", ShareOutcome::TimedOut, RGBColor(230, 150, 0)),
+        ("Cancelled", ShareOutcome::Cancelled, RED),
+    ];
+
+    let series = Vec::from_iter(outcomes.iter().map(|&(name, outcome, color)| (name, color, per_day(outcome))));
+    let max = series.iter().flat_map(|(_, _, counts)| counts.iter().map(|(_, count)| *count)).max().unwrap_or(0).max(1);
+
+    render(|root| {
+        let mut chart = ChartBuilder::on(root)
+            .caption(format!("Shared copies over the last {} days", HISTORY_DAYS), ("sans-serif", 40))
+            .margin(20)
+            .margin_right(50)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(-(HISTORY_DAYS as i64 - 1)..0, 0..max + max / 10 + 1)?;
+
+        chart.configure_mesh()
+            .x_label_style(("sans-serif", 20))
+            .y_label_style(("sans-serif", 20))
+            .x_label_formatter(&|days| match days {
+                0 => "today".to_string(),
+                _ => format!("{}d ago", -days),
+            })
+            .draw()?;
+
+        for (name, color, counts) in series.iter() {
+            chart.draw_series(LineSeries::new(counts.iter().copied(), color.stroke_width(3)))?
+                .label(*name)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(3)));
+        }
+
+        chart.configure_series_labels()
+            .label_font(("sans-serif", 20))
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+
+        Ok(())
+    })
+}
+
+
+/// renders the global charts into a directory, for running from the command line with `export-charts <directory>`
+pub async fn export(directory: &str) {
+
+    // the database is only read, so this can run while the bot is up
+    let database = crate::Database::new();
+    let stats = crate::stats::collect(&database, None).await;
+
+    std::fs::create_dir_all(directory).unwrap_or_else(|_| panic!("could not create {:?}", directory));
+
+    for (name, png) in render_all(&stats).expect("could not render the charts") {
+        let path = format!("{}/{}", directory, name);
+        std::fs::write(&path, png).unwrap_or_else(|_| panic!("could not write {:?}", path));
+        println!("wrote {}", path);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CosmeticCategory;
+
+    #[test]
+    fn renders_with_the_built_in_font() {
+        let stats = Stats {
+            needs_by_category: Vec::from_iter(CosmeticCategory::ALL.iter().map(|&category| (category, vec![(format!("{} cosmetic", category), 3)]))),
+            total_needs: 18,
+            active_users: 4,
+            median_need_age: None,
+            shares_created: 1,
+            copies_shared: 1,
+            copies_claimed: 1,
+            copies_timed_out: 0,
+            copies_cancelled: 0,
+            median_time_to_claim: Some(30),
+            top_finders: Vec::new(),
+            share_history: vec![(crate::now(), ShareOutcome::Claimed)],
+        };
+
+        let charts = render_all(&stats).unwrap();
+
+        assert_eq!(charts.len(), 3);
+        assert!(charts.iter().all(|(_, png)| png.starts_with(b"\x89PNG")));
+    }
+}
@@ -0,0 +1,107 @@
+
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use serenity::all::User;
+
+use crate::needs::NeedRecord;
+
+
+/// how long users have to say they want a cosmetic before a winner is picked, when not using `ClaimMode::FirstClick`
+pub const CLAIM_WINDOW: Duration = Duration::from_secs(60);
+
+/// how long only high priority users are pinged and can claim, before everyone else is pinged
+pub const PRIORITY_WINDOW: Duration = Duration::from_secs(30);
+
+
+/// how the user that gets a shared cosmetic is decided
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimMode {
+    /// whoever clicks claim first
+    #[name = "First Click"]
+    FirstClick,
+    /// everyone interested during the claim window gets an equal chance
+    #[name = "Lottery"]
+    Lottery,
+    /// whoever has needed it the longest out of everyone interested during the claim window
+    #[name = "Longest Waiting"]
+    LongestWaiting,
+}
+
+impl ClaimMode {
+    pub fn to_id(self) -> &'static str {
+        match self {
+            Self::FirstClick => "first_click",
+            Self::Lottery => "lottery",
+            Self::LongestWaiting => "longest_waiting",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "first_click" => Some(Self::FirstClick),
+            "lottery" => Some(Self::Lottery),
+            "longest_waiting" => Some(Self::LongestWaiting),
+            _ => None,
+        }
+    }
+
+    pub fn uses_window(self) -> bool {
+        self != Self::FirstClick
+    }
+
+    /// explains to everyone how the winner will be picked
+    pub fn describe(self) -> String {
+        match self {
+            Self::FirstClick => "The first person to click \"Claim\" gets it".to_string(),
+            Self::Lottery => format!("Click \"I Want\" on what you need in the next {} seconds to enter the draw, the winner is picked at random with pinged users going first", CLAIM_WINDOW.as_secs()),
+            Self::LongestWaiting => format!("Click \"I Want\" on what you need in the next {} seconds, whoever has needed it the longest gets it with pinged users going first", CLAIM_WINDOW.as_secs()),
+        }
+    }
+
+    /// explains how the winner was picked, out of how many were interested. there's nothing to explain with only one
+    pub fn describe_pick(self, entrants: usize) -> String {
+        match self {
+            Self::FirstClick => String::new(),
+            _ if entrants <= 1 => String::new(),
+            Self::Lottery => format!(", drawn at random from {} interested", entrants),
+            Self::LongestWaiting => format!(", who waited the longest out of {} interested", entrants),
+        }
+    }
+}
+
+
+/// orders the interested users by who should get the cosmetic first.
+///
+/// users that were pinged because they need it always go before walk-ins, and higher priority needs go first
+pub fn rank_claimants(mode: ClaimMode, interested: &[User], need_records: &[NeedRecord]) -> Vec<User> {
+
+    let record = |user: &User| need_records.iter().position(|record| record.user_id == user.id.to_string());
+
+    // needs from before timestamps were recorded are the oldest, then it's the order they were added in
+    let waited = |user: &User| {
+        let position = record(user)?;
+        Some((need_records[position].priority, need_records[position].created_at.unwrap_or(0), position))
+    };
+
+    let mut pinged = Vec::from_iter(interested.iter().filter(|user| record(user).is_some()).cloned());
+    let mut walk_ins = Vec::from_iter(interested.iter().filter(|user| record(user).is_none()).cloned());
+
+    match mode {
+        // walk-ins stay in the order they clicked
+        ClaimMode::FirstClick | ClaimMode::LongestWaiting => {
+            pinged.sort_by_key(waited);
+        },
+        ClaimMode::Lottery => {
+            let mut rng = rand::thread_rng();
+            pinged.shuffle(&mut rng);
+            walk_ins.shuffle(&mut rng);
+
+            // stable so it's still random within each priority
+            pinged.sort_by_key(|user| record(user).map(|position| need_records[position].priority));
+        },
+    }
+
+    pinged.extend(walk_ins);
+    pinged
+}
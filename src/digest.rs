@@ -0,0 +1,236 @@
+use poise::{ChoiceParameter, CreateReply};
+use serenity::all::UserId;
+use serenity::builder::CreateMessage;
+use serenity::prelude::*;
+
+use crate::shares::{group_copies, ShareOutcome, ShareRecord};
+use crate::{dm_user, split_message, Database, Error};
+
+
+const DAY: u64 = 60 * 60 * 24;
+
+/// how long to wait before trying again when a digest couldn't be sent
+const RETRY_AFTER: u64 = 60 * 60 * 6;
+
+/// digests that still can't be sent after this many tries are skipped, and the next one starts from then
+const MAX_ATTEMPTS: u64 = 4;
+
+
+/// how often a user gets dm'd the shares they missed
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DigestFrequency {
+    #[default]
+    #[name = "Off"]
+    Off,
+    #[name = "Daily"]
+    Daily,
+    #[name = "Weekly"]
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn to_id(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "off" => Some(Self::Off),
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            _ => None,
+        }
+    }
+
+    /// how long between digests, in seconds
+    pub fn period(self) -> Option<u64> {
+        match self {
+            Self::Off => None,
+            Self::Daily => Some(DAY),
+            Self::Weekly => Some(DAY * 7),
+        }
+    }
+}
+
+
+#[poise::command(slash_command)]
+pub async fn digest(
+    ctx: crate::Context<'_>,
+    #[description = "How often to get a dm of the things you need that were found"]
+    frequency: DigestFrequency,
+) -> Result<(), Error> {
+
+
+    let user_id = ctx.author().id.to_string();
+
+    let mut settings = ctx.data().database.user_settings(&user_id);
+    settings.digest = frequency;
+
+    // the first digest covers from now on
+    settings.last_digest = Some(crate::now());
+    settings.digest_failures = 0;
+    settings.digest_failed_at = None;
+
+    ctx.data().database.set_user_settings(&user_id, &settings);
+
+    let content = match frequency {
+        DigestFrequency::Off => "You won't get digests anymore".to_string(),
+        frequency => format!("You'll get a **{}** dm of the things you need that were found, and who has spares of them", frequency.name().to_lowercase()),
+    };
+
+    ctx.send(
+        CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+    ).await.unwrap();
+
+
+    Ok(())
+}
+
+
+/// dms everyone that's due a digest the shares of things they need since their last one
+pub async fn send_digests(ctx: &Context, database: &Database) {
+
+    let now = crate::now();
+
+    let mut shares: Option<Vec<ShareRecord>> = None;
+
+    for user_id in database.users_with_settings() {
+        let mut settings = database.user_settings(&user_id);
+
+        let Some(period) = settings.digest.period() else {
+            continue;
+        };
+
+        // whatever was missed while away is in the first digest after they're back
+        if settings.on_vacation(now) {
+            continue;
+        }
+
+        let since = settings.last_digest.unwrap_or(now);
+
+        if now < since + period {
+            continue;
+        }
+
+        // users with their dms closed aren't tried every time the job runs
+        if settings.digest_failed_at.is_some_and(|failed_at| now < failed_at + RETRY_AFTER) {
+            continue;
+        }
+
+        let Ok(id) = user_id.parse::<u64>() else {
+            continue;
+        };
+
+        // only read the shares once there's someone to send them to
+        let shares = shares.get_or_insert_with(|| database.shares());
+
+        // the next digest starts from this one, unless it couldn't be sent and gets tried again later
+        let mut sent = true;
+
+        if let Some(content) = describe(database, &user_id, shares, since) {
+            for content in split_message(&content) {
+                if dm_user(ctx, UserId::new(id), CreateMessage::new().content(content)).await.is_err() {
+                    println!("could not dm user {} their digest", id);
+                    sent = false;
+                    break;
+                }
+            }
+        }
+
+        if !sent {
+            settings.digest_failures += 1;
+            settings.digest_failed_at = Some(now);
+        }
+
+        if sent || settings.digest_failures >= MAX_ATTEMPTS {
+            settings.last_digest = Some(now);
+            settings.digest_failures = 0;
+            settings.digest_failed_at = None;
+        }
+
+        database.set_user_settings(&user_id, &settings);
+    }
+}
+
+/// lists the shares of things the user still needs since a time, or `None` if there weren't any
+fn describe(database: &Database, user_id: &str, shares: &[ShareRecord], since: u64) -> Option<String> {
+
+    let mut missed = Vec::from_iter(
+        shares.iter()
+        .filter(|share| share.created_at >= since && share.finder != user_id)
+        .filter(|share| database.needs(user_id, &share.cosmetic))
+        .cloned()
+    );
+
+    if missed.is_empty() {
+        return None;
+    }
+
+    missed.sort_by_key(|share| (share.created_at, share.copy));
+
+    // each cosmetic in a share gets one line, however many copies it had
+    let missed = group_copies(missed);
+
+    let list = missed.iter().fold(String::new(), |acc, copies| {
+        let share = &copies[0];
+
+        let cosmetic = match copies.len() {
+            1 => format!("**{}**", share.cosmetic),
+            count => format!("**{}** x{}", share.cosmetic, count),
+        };
+
+        format!("{}\n- {} found by <@{}>, {} {}", acc, cosmetic, share.finder, describe_outcomes(copies), share.link())
+    });
+
+    // anyone with a spare could trade it
+    let mut cosmetics = Vec::from_iter(missed.iter().map(|copies| copies[0].cosmetic.as_str()));
+    cosmetics.sort();
+    cosmetics.dedup();
+
+    let spares = cosmetics.iter().fold(String::new(), |acc, cosmetic| {
+        let owners = Vec::from_iter(database.who_has_spare(cosmetic).into_iter().filter(|owner| owner != user_id).map(|owner| format!("<@{}>", owner)));
+
+        match owners.is_empty() {
+            true => acc,
+            false => format!("{}\n- **{}**: {}", acc, cosmetic, owners.join(", ")),
+        }
+    });
+
+    let spares = match spares.is_empty() {
+        true => String::new(),
+        false => format!("\n\nThese people have spares of them, use `/trades` to see if you can swap{}", spares),
+    };
+
+    Some(format!("Here's what was found that you need since your last digest{}{}", list, spares))
+}
+
+/// what happened to the copies, with copies that went the same way counted together
+fn describe_outcomes(copies: &[ShareRecord]) -> String {
+    let mut outcomes: Vec<(String, usize)> = Vec::new();
+
+    for copy in copies {
+        let outcome = match (copy.outcome, &copy.claimant) {
+            (ShareOutcome::Claimed, Some(claimant)) => format!("claimed by <@{}>", claimant),
+            (ShareOutcome::Claimed, None) => "claimed".to_string(),
+            (ShareOutcome::Open, _) => "still up for grabs".to_string(),
+            (ShareOutcome::TimedOut, _) => "no one claimed it".to_string(),
+            (ShareOutcome::Cancelled, _) => "cancelled".to_string(),
+        };
+
+        match outcomes.iter_mut().find(|(existing, _)| *existing == outcome) {
+            Some((_, count)) => *count += 1,
+            None => outcomes.push((outcome, 1)),
+        }
+    }
+
+    Vec::from_iter(outcomes.into_iter().map(|(outcome, count)| match count {
+        1 => outcome,
+        count => format!("{} x{}", outcome, count),
+    })).join(", ")
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use poise::ChoiceParameter;
+use serenity::all::{ChannelId, GuildId, RoleId};
+
+use crate::claiming::ClaimMode;
+
+
+/// how the users that need a shared cosmetic are told about it
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq)]
+pub enum PingStyle {
+    /// pinged in the share's channel
+    #[name = "Mention"]
+    Mention,
+    /// named in the share's channel without pinging, and sent a dm instead
+    #[name = "Direct Message"]
+    DirectMessage,
+    /// named in the share's channel without pinging
+    #[name = "Silent"]
+    Silent,
+}
+
+impl PingStyle {
+    pub fn to_id(self) -> &'static str {
+        match self {
+            Self::Mention => "mention",
+            Self::DirectMessage => "dm",
+            Self::Silent => "silent",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "mention" => Some(Self::Mention),
+            "dm" => Some(Self::DirectMessage),
+            "silent" => Some(Self::Silent),
+            _ => None,
+        }
+    }
+}
+
+
+/// what the bot shows about users outside of the server
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq)]
+pub enum PrivacyScope {
+    /// only counts members of this server
+    #[name = "Server"]
+    Server,
+    /// also shows counts across every server
+    #[name = "Global"]
+    Global,
+}
+
+impl PrivacyScope {
+    pub fn to_id(self) -> &'static str {
+        match self {
+            Self::Server => "server",
+            Self::Global => "global",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "server" => Some(Self::Server),
+            "global" => Some(Self::Global),
+            _ => None,
+        }
+    }
+}
+
+
+/// options each server's admins can set with `/config`, stored as `key value` lines in `database/guilds/<guild id>.txt`.
+///
+/// dms use the defaults
+#[derive(Clone)]
+pub struct GuildSettings {
+    /// where the bot posts shares, instead of the channel the command was used in
+    pub announcement_channel: Option<ChannelId>,
+    /// whether claimed shares get a thread for the finder and claimants to sort out the handoffs, off unless a server turns it on
+    pub share_threads: bool,
+    /// how the person that gets a share is picked when the finder doesn't say
+    pub claim_mode: ClaimMode,
+    /// how long people have to claim a share before it times out
+    pub claim_timeout: Duration,
+    /// the longest a finder can give people to claim with the `duration` option of `/foundsomething`
+    pub max_claim_timeout: Duration,
+    /// how long the bot waits for someone to pick something from a menu or button prompt
+    pub select_timeout: Duration,
+    pub ping_style: PingStyle,
+    pub privacy_scope: PrivacyScope,
+    /// only members with one of these roles can use the bot, everyone can if it's empty
+    pub allowed_roles: Vec<RoleId>,
+    /// the language the bot replies in, only `en` has been written so far
+    pub locale: String,
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        GuildSettings {
+            announcement_channel: None,
+            share_threads: false,
+            claim_mode: ClaimMode::FirstClick,
+            claim_timeout: Duration::from_secs(60 * 3),
+            max_claim_timeout: Duration::from_secs(60 * 30),
+            select_timeout: Duration::from_secs(60),
+            ping_style: PingStyle::Mention,
+            privacy_scope: PrivacyScope::Global,
+            allowed_roles: Vec::new(),
+            locale: "en".to_string(),
+        }
+    }
+}
+
+impl GuildSettings {
+    pub fn from_key_values(values: &HashMap<String, String>) -> Self {
+        let mut settings = GuildSettings::default();
+
+        let seconds = |key: &str| values.get(key).and_then(|secs| secs.parse().ok()).map(Duration::from_secs);
+
+        settings.announcement_channel = values.get("announcement_channel").and_then(|id| id.parse().ok()).map(ChannelId::new);
+
+        if let Some(share_threads) = values.get("share_threads") {
+            settings.share_threads = share_threads == "true";
+        }
+
+        if let Some(claim_mode) = values.get("claim_mode").and_then(|id| ClaimMode::from_id(id)) {
+            settings.claim_mode = claim_mode;
+        }
+
+        if let Some(claim_timeout) = seconds("claim_timeout") {
+            settings.claim_timeout = claim_timeout;
+        }
+
+        if let Some(max_claim_timeout) = seconds("max_claim_timeout") {
+            settings.max_claim_timeout = max_claim_timeout;
+        }
+
+        if let Some(select_timeout) = seconds("select_timeout") {
+            settings.select_timeout = select_timeout;
+        }
+
+        if let Some(ping_style) = values.get("ping_style").and_then(|id| PingStyle::from_id(id)) {
+            settings.ping_style = ping_style;
+        }
+
+        if let Some(privacy_scope) = values.get("privacy_scope").and_then(|id| PrivacyScope::from_id(id)) {
+            settings.privacy_scope = privacy_scope;
+        }
+
+        if let Some(roles) = values.get("allowed_roles") {
+            settings.allowed_roles = Vec::from_iter(roles.split(',').filter_map(|id| id.parse().ok()).map(RoleId::new));
+        }
+
+        if let Some(locale) = values.get("locale") {
+            settings.locale = locale.clone();
+        }
+
+        settings
+    }
+
+    pub fn to_key_values(&self) -> Vec<(&'static str, String)> {
+        let mut values = vec![
+            ("share_threads", self.share_threads.to_string()),
+            ("claim_mode", self.claim_mode.to_id().to_string()),
+            ("claim_timeout", self.claim_timeout.as_secs().to_string()),
+            ("max_claim_timeout", self.max_claim_timeout.as_secs().to_string()),
+            ("select_timeout", self.select_timeout.as_secs().to_string()),
+            ("ping_style", self.ping_style.to_id().to_string()),
+            ("privacy_scope", self.privacy_scope.to_id().to_string()),
+            ("allowed_roles", Vec::from_iter(self.allowed_roles.iter().map(|id| id.to_string())).join(",")),
+            ("locale", self.locale.clone()),
+        ];
+
+        if let Some(channel) = self.announcement_channel {
+            values.push(("announcement_channel", channel.to_string()));
+        }
+
+        values
+    }
+
+    /// how long a share is open for, using the finder's duration if they gave one
+    pub fn claim_timeout(&self, requested: Option<Duration>) -> Duration {
+        requested.map_or(self.claim_timeout, |requested| requested.min(self.max_claim_timeout))
+    }
+
+    /// checks the settings make sense together, returning what's wrong if they don't
+    pub fn validate(&self) -> Result<(), String> {
+        if self.claim_timeout > self.max_claim_timeout {
+            return Err("The claim timeout can't be longer than the most a finder can give people".to_string());
+        }
+
+        if !valid_locale(&self.locale) {
+            return Err(format!("\"{}\" isn't a locale, use a language code like `en` or `en-US`", self.locale));
+        }
+
+        Ok(())
+    }
+
+    pub fn describe(&self) -> String {
+        let channel = match self.announcement_channel {
+            Some(channel) => format!("<#{}>", channel),
+            None => "wherever `/foundsomething` is used".to_string(),
+        };
+
+        let roles = match self.allowed_roles.is_empty() {
+            true => "everyone".to_string(),
+            false => Vec::from_iter(self.allowed_roles.iter().map(|id| format!("<@&{}>", id))).join(", "),
+        };
+
+        format!(
+            "**Shares channel:** {}\n**Thread for each share:** {}\n**Claim mode:** {}\n**Claim timeout:** {}, and finders can give people up to {}\n**Menu timeout:** {}\n**Ping style:** {}\n**Privacy scope:** {}\n**Allowed roles:** {}\n**Locale:** {}",
+            channel,
+            if self.share_threads { "yes" } else { "no" },
+            self.claim_mode.name(),
+            crate::format_duration(self.claim_timeout.as_secs()),
+            crate::format_duration(self.max_claim_timeout.as_secs()),
+            crate::format_duration(self.select_timeout.as_secs()),
+            self.ping_style.name(),
+            self.privacy_scope.name(),
+            roles,
+            self.locale,
+        )
+    }
+}
+
+
+/// a language code with an optional region, like `en` or `en-US`
+fn valid_locale(locale: &str) -> bool {
+    let mut parts = locale.split('-');
+
+    let language = parts.next().unwrap_or_default();
+    let region = parts.next();
+
+    (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase())
+        && region.is_none_or(|region| region.len() == 2 && region.chars().all(|c| c.is_ascii_uppercase()))
+        && parts.next().is_none()
+}
+
+
+/// every server's settings, kept in memory after they're first read so commands don't have to read the file each time
+#[derive(Default)]
+pub struct GuildSettingsStore {
+    cache: Mutex<HashMap<GuildId, GuildSettings>>,
+}
+
+impl GuildSettingsStore {
+    pub fn get(&self, guild_id: GuildId) -> GuildSettings {
+        let mut cache = self.cache.lock().unwrap();
+
+        cache.entry(guild_id)
+            .or_insert_with(|| GuildSettings::from_key_values(&crate::read_key_values(&create_guild_path(guild_id))))
+            .clone()
+    }
+
+    pub fn set(&self, guild_id: GuildId, settings: GuildSettings) {
+        crate::write_key_values(&create_guild_path(guild_id), settings.to_key_values());
+        self.cache.lock().unwrap().insert(guild_id, settings);
+    }
+
+    /// forgets a server's settings once the bot has been removed from it
+    pub fn remove(&self, guild_id: GuildId) {
+        let _ = std::fs::remove_file(create_guild_path(guild_id));
+        self.cache.lock().unwrap().remove(&guild_id);
+    }
+}
+
+fn create_guild_path(guild_id: GuildId) -> String {
+    format!("database/guilds/{}.txt", guild_id)
+}
@@ -0,0 +1,109 @@
+use poise::CreateReply;
+use serenity::all::{ButtonStyle, FullEvent, Interaction};
+use serenity::builder::{CreateActionRow, CreateButton};
+
+use crate::shares::{group_copies, ShareOutcome};
+use crate::sharing::{inbox_claim_id, parse_inbox_claim_id};
+use crate::{reply_privately, Context, Data, Error};
+
+
+/// there's room for a claim button for every share
+const MAX_INBOX_SIZE: usize = 10;
+
+
+#[poise::command(slash_command)]
+pub async fn inbox(
+    ctx: Context<'_>,
+    #[description = "How many of the latest shares to show, defaults to 10"]
+    #[min = 1]
+    #[max = 10]
+    count: Option<usize>,
+) -> Result<(), Error> {
+
+
+    let user_id = ctx.author().id.to_string();
+    let database = &ctx.data().database;
+
+    let count = count.unwrap_or(MAX_INBOX_SIZE).min(MAX_INBOX_SIZE);
+
+    let mut records = Vec::from_iter(
+        database.shares().into_iter()
+        .filter(|share| share.finder != user_id && database.needs(&user_id, &share.cosmetic))
+    );
+
+    records.sort_by_key(|share| std::cmp::Reverse(share.created_at));
+
+    // every copy has its own record, but each cosmetic in a share is only listed once
+    let mut entries = group_copies(records);
+    entries.truncate(count);
+
+    if entries.is_empty() {
+        ctx.send(
+            CreateReply::default()
+                .content("Nothing you need has been shared yet")
+                .ephemeral(true)
+        ).await.unwrap();
+
+        return Ok(());
+    }
+
+    let live_shares = ctx.data().live_shares.lock().unwrap().clone();
+
+    let mut buttons = Vec::new();
+
+    let list = entries.iter().fold(String::new(), |acc, copies| {
+        let share = &copies[0];
+
+        let open = copies.iter().filter(|copy| copy.outcome == ShareOutcome::Open).count();
+
+        // copies can only be claimed while the share is waiting for claims
+        let status = if open > 0 && live_shares.contains(&share.id) {
+            buttons.push(CreateButton::new(inbox_claim_id(share.id, &share.cosmetic)).style(ButtonStyle::Success).label(format!("Claim {}", share.cosmetic)));
+            format!("open, {} left", open)
+        } else if copies.iter().any(|copy| copy.claimant.as_ref() == Some(&user_id)) {
+            "claimed by you".to_string()
+        } else if copies.iter().any(|copy| copy.outcome == ShareOutcome::Claimed) {
+            "claimed".to_string()
+        } else if copies.iter().all(|copy| copy.outcome == ShareOutcome::Cancelled) {
+            "cancelled".to_string()
+        } else {
+            "expired".to_string()
+        };
+
+        format!("{}\n- **{}** found by <@{}> <t:{}:R>, {} {}", acc, share.cosmetic, share.finder, share.created_at, status, share.link())
+    });
+
+    let components = Vec::from_iter(buttons.chunks(5).map(|buttons| CreateActionRow::Buttons(buttons.to_vec())));
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!("The latest shares of things you need{}", list))
+            .components(components)
+            .ephemeral(true)
+    ).await.unwrap();
+
+
+    Ok(())
+}
+
+
+/// answers claim buttons in old `/inbox` messages for shares that aren't waiting for claims anymore,
+/// since there's nothing else listening for them
+pub async fn handle_event(ctx: &serenity::all::Context, event: &FullEvent, data: &Data) -> Result<(), Error> {
+
+    let FullEvent::InteractionCreate { interaction: Interaction::Component(interaction) } = event else {
+        return Ok(());
+    };
+
+    let Some((share_id, cosmetic)) = parse_inbox_claim_id(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+
+    if data.live_shares.lock().unwrap().contains(&share_id) {
+        return Ok(());
+    }
+
+    reply_privately(ctx, interaction, format!("**{}** isn't up for grabs anymore", cosmetic)).await;
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::prelude::*;
+
+use crate::{digest, membership, quiet_hours, stale_needs, Database};
+
+
+/// how often the background jobs check if there's anything to do
+const JOB_INTERVAL: Duration = Duration::from_secs(60);
+
+/// reading everyone's needs is slow, and check ins go by days anyway
+const CHECK_IN_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// leaving servers is mostly caught as it happens, the sweep catches whatever was missed while the bot was offline
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// gives the cache time to load every server before the first sweep
+const SWEEP_DELAY: Duration = Duration::from_secs(60 * 10);
+
+
+/// runs the jobs that aren't started by a command, for as long as the bot is running
+pub async fn run(ctx: Context, database: Arc<Database>) {
+
+    let mut interval = tokio::time::interval(JOB_INTERVAL);
+    let mut check_in_interval = tokio::time::interval(CHECK_IN_INTERVAL);
+    let mut sweep_interval = tokio::time::interval_at(tokio::time::Instant::now() + SWEEP_DELAY, SWEEP_INTERVAL);
+
+    stale_needs::resume(&ctx, &database);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                quiet_hours::send_summaries(&ctx, &database).await;
+                digest::send_digests(&ctx, &database).await;
+                database.purge_tombstones();
+            },
+            _ = check_in_interval.tick() => {
+                stale_needs::send_check_ins(&ctx, &database).await;
+            },
+            _ = sweep_interval.tick() => {
+                // a sweep can take a while, so the other jobs don't wait for it
+                tokio::spawn(membership::sweep(ctx.clone(), database.clone()));
+            },
+        }
+    }
+}
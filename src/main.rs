@@ -1,33 +1,95 @@
 
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::Duration;
 
 use poise::CreateReply;
 use serenity::all::{ButtonStyle, ComponentInteractionDataKind};
-use serenity::builder::{CreateActionRow, CreateButton, CreateInteractionResponse, CreateMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption};
+use serenity::builder::{CreateActionRow, CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateEmbedFooter, CreateButton, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption};
+use serenity::futures::StreamExt;
 use serenity::prelude::*;
 
+mod charts;
+mod claiming;
+mod digest;
+mod guild_settings;
+mod inbox;
+mod jobs;
+mod membership;
+mod needs;
+mod notifications;
+mod quiet_hours;
+mod shares;
+mod snooze;
+mod stale_needs;
+mod sharing;
+mod stats;
+mod trades;
+mod user_settings;
+
+use needs::{NeedRecord, Priority};
+use claiming::ClaimMode;
+use guild_settings::{GuildSettings, GuildSettingsStore, PingStyle, PrivacyScope};
+use shares::{ShareOutcome, ShareRecord};
+use user_settings::UserSettings;
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
 // User data, which is stored and accessible in all command invocations
 pub struct Data {
-    database: Database,
+    /// shared with the background jobs
+    database: Arc<Database>,
+    guild_settings: GuildSettingsStore,
+    /// keys of trades that have already been dm'd to their users, and when they were
+    proposed_trades: Mutex<HashMap<String, u64>>,
+    /// status message ids of the shares that are waiting for claims right now, which is when `/inbox` claims work
+    live_shares: Arc<std::sync::Mutex<HashSet<u64>>>,
 }
 
 
 #[tokio::main]
 async fn main() {
 
+    // `export-charts <directory>` renders the global stats charts instead of running the bot
+    let args = Vec::from_iter(std::env::args());
+    if args.get(1).map(String::as_str) == Some("export-charts") {
+        charts::export(args.get(2).map(String::as_str).unwrap_or("charts")).await;
+        return;
+    }
+
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
             commands: vec![
-                foundsomething(),
+                sharing::foundsomething(),
                 needsomething(),
                 whatdoineed(),
                 dontneed(),
+                gotspare(),
+                nospare(),
+                trades(),
+                whoneeds(),
+                visibility(),
+                notifications::notifications(),
+                quiet_hours::quiethours(),
+                digest::digest(),
+                inbox::inbox(),
+                snooze::snooze(),
+                snooze::vacation(),
+                stale_needs::unarchive(),
+                stats(),
                 help(),
                 forgetme(),
+                restoreme(),
+                config(),
             ],
+            command_check: Some(|ctx| Box::pin(has_allowed_role(ctx))),
+            event_handler: |ctx, event, _framework, data| Box::pin(handle_event(ctx, event, data)),
+            // any command counts as still being around, so their needs aren't asked about
+            pre_command: |ctx| {
+                Box::pin(async move {
+                    ctx.data().database.set_last_active(&ctx.author().id.to_string(), now());
+                })
+            },
             on_error: |_error| {
                 Box::pin(async move {
                     println!("got an error");
@@ -38,8 +100,17 @@ async fn main() {
         .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+
+                let database = Arc::new(Database::new());
+                database.prepare();
+
+                tokio::spawn(jobs::run(ctx.clone(), database.clone()));
+
                 Ok(Data {
-                    database: Database::new(),
+                    proposed_trades: Mutex::new(database.proposed_trades()),
+                    database,
+                    guild_settings: GuildSettingsStore::default(),
+                    live_shares: Arc::new(std::sync::Mutex::new(HashSet::new())),
                 })
             })
         })
@@ -48,7 +119,13 @@ async fn main() {
 
     let token = std::fs::read_to_string("token.txt").expect("could not read token in token.txt");
 
-    let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
+    // members leaving are only sent with the server members intent, which is privileged and has to be turned on in the developer portal first.
+    // it's opt in with `--member-events`, otherwise users that left are only caught by the daily sweep
+    let mut intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
+
+    if args.iter().any(|arg| arg == "--member-events") {
+        intents |= GatewayIntents::GUILD_MEMBERS;
+    }
 
     let mut client = Client::builder(token, intents)
         // .event_handler(Handler)
@@ -66,107 +143,309 @@ async fn main() {
 
 
 
-const CLAIM_TIMEOUT: Duration = Duration::from_secs(60 * 3);
-
 #[poise::command(slash_command)]
-async fn foundsomething(
+async fn needsomething(
     ctx: Context<'_>,
+    #[description = "High priority needs get pinged first, defaults to normal or leaves an existing need's priority alone"]
+    priority: Option<Priority>,
 ) -> Result<(), Error> {
 
-    let author_id = ctx.author().id;
-
-    // this is the main reply message, visible to everyone and gets updated with the status of the share
     let status_reply = ctx.send(
-        CreateReply::default().content(format!("<@{}> has found a cosmetic", author_id))
+        CreateReply::default()
+            .content("Select the cosmetic you need")
+            .ephemeral(true)
     ).await.unwrap();
 
 
     // get the cosmetic
     let Some(cosmetic) = cosmetic_select(ctx).await else {
-        status_reply.edit(ctx, CreateReply::default().content(format!("<@{}> found a cosmetic but cancelled", author_id))).await.unwrap();
+        status_reply.edit(ctx, CreateReply::default().content("Cancelled")).await.unwrap();
         return Ok(());
     };
 
 
-    // update the status with the cosmetic
-    status_reply.edit(ctx, CreateReply::default().content(format!("<@{}> has found **{}**", author_id, cosmetic))).await.unwrap();
+    if let Some(record) = ctx.data().database.need_record(&ctx.author().id.to_string(), &cosmetic) {
+        // running it again counts as confirming they still need it
+        ctx.data().database.confirm(&cosmetic, &ctx.author().id.to_string());
 
+        let content = match priority {
+            Some(priority) if priority != record.priority => {
+                ctx.data().database.set_priority(&cosmetic, &ctx.author().id.to_string(), priority);
+                format!("you already need **{}**, it's now {}", cosmetic, priority)
+            },
+            _ => format!("you already need **{}**, {}", cosmetic, record.describe_age()),
+        };
 
-    // send a message to ping the users that need the cosmetic
-    let needed_users = ctx.data().database.who_needs(&cosmetic);
+        status_reply.edit(ctx, CreateReply::default().content(content)).await.unwrap();
+        return Ok(())
+    }
 
-    let content = if needed_users.len() == 0 {
-        format!("<@{}> has found **{}** but no one needs it, you can still claim it if you need it\n\n", author_id, cosmetic)
-    } else {
 
-        let needed_users = needed_users.iter()
-        .fold(String::new(), |acc, user| format!("{} <@{}>", acc, user));
+    // add to database
+    let priority = priority.unwrap_or_default();
+    ctx.data().database.add(&cosmetic, &ctx.author().id.to_string(), priority);
+    status_reply.edit(ctx, CreateReply::default().content(format!("you now need **{}** ({})", cosmetic, priority))).await.unwrap();
 
-        format!("<@{}> has found **{}**\n\n{}\n\nYou can still claim it if you weren't pinged, and if you have it but got pinged click \"Already Have It\"", author_id, cosmetic, needed_users)
-    };
 
-    let claim_reply = ctx.send(
+    trades::propose_trades(ctx.serenity_context(), ctx.data(), &ctx.author().id.to_string()).await;
+
+
+    Ok(())
+}
+
+
+
+/// how many cosmetics are listed on each page of `/whatdoineed`, has to fit in one select menu
+const NEEDS_PAGE_SIZE: usize = 20;
+
+/// how long removing needs from `/whatdoineed` can be undone for
+const UNDO_WINDOW: Duration = Duration::from_secs(30);
+
+/// needs removed from `/whatdoineed` that can be put back, with the cosmetic they were for
+type RemovedNeeds = Vec<(NeedRecord, String)>;
+
+/// a user's needs split into pages that each only have cosmetics from one category
+struct NeedsView {
+    needs: Vec<(CosmeticCategory, Vec<(String, NeedRecord)>)>,
+    pages: Vec<(String, Vec<(String, NeedRecord)>)>,
+    total: usize,
+}
+
+impl NeedsView {
+    fn load(database: &Database, user_id: &str, category: Option<CosmeticCategory>) -> NeedsView {
+
+        let needs = Vec::from_iter(
+            database.needed_by_category(user_id).into_iter()
+            .filter(|(c, _)| category.is_none() || category == Some(*c))
+        );
+
+        let total = needs.iter().map(|(_, cosmetics)| cosmetics.len()).sum();
+
+        let mut pages = Vec::new();
+
+        for (category, cosmetics) in needs.iter() {
+            let chunks = Vec::from_iter(cosmetics.chunks(NEEDS_PAGE_SIZE));
+
+            for (i, chunk) in chunks.iter().enumerate() {
+                let title = if chunks.len() > 1 {
+                    format!("{} ({}/{})", category, i + 1, chunks.len())
+                } else {
+                    category.to_string()
+                };
+
+                pages.push((title, chunk.to_vec()));
+            }
+        }
+
+        NeedsView {
+            needs,
+            pages,
+            total,
+        }
+    }
+
+    fn page(&self, n: usize, selected: &HashSet<String>, can_undo: bool) -> CreateReply {
+
+        let Some((title, cosmetics)) = self.pages.get(n) else {
+            let mut components = Vec::new();
+
+            if can_undo {
+                components.push(CreateActionRow::Buttons(vec![
+                    CreateButton::new("undo").label("Undo").style(ButtonStyle::Secondary),
+                ]));
+            }
+
+            return CreateReply::default()
+                .ephemeral(true)
+                .embed(CreateEmbed::new().title("You don't need anything"))
+                .components(components);
+        };
+
+        let description = cosmetics.iter().fold(String::new(), |acc, (cosmetic, record)| {
+            match record.priority {
+                Priority::Normal => format!("{}**{}** - {}\n", acc, cosmetic, record.describe_age()),
+                priority => format!("{}**{}** - {}, {}\n", acc, cosmetic, priority, record.describe_age()),
+            }
+        });
+
+        let mut buttons = vec![
+            CreateButton::new("back").label("< Page").disabled(self.pages.len() == 1),
+            CreateButton::new("next").label("Page >").disabled(self.pages.len() == 1),
+            CreateButton::new("remove").label("Remove Selected").style(ButtonStyle::Danger).disabled(selected.is_empty()),
+            CreateButton::new("owned").label("Mark As Owned").style(ButtonStyle::Success).disabled(selected.is_empty()),
+        ];
+
+        if can_undo {
+            buttons.push(CreateButton::new("undo").label("Undo").style(ButtonStyle::Secondary));
+        } else {
+            buttons.push(CreateButton::new("export").label("Export").style(ButtonStyle::Secondary));
+        }
+
         CreateReply::default()
-            .content(content)
+            .ephemeral(true)
+            .embed(
+                CreateEmbed::new()
+                    .title(title)
+                    .description(description)
+                    .footer(CreateEmbedFooter::new(format!("Page {}/{} - you need {} cosmetics", n + 1, self.pages.len(), self.total)))
+            )
             .components(vec![
-                CreateActionRow::Buttons(vec![
-                    CreateButton::new("claim").style(ButtonStyle::Success).label("Claim"),
-                    CreateButton::new("cancel").style(ButtonStyle::Danger).label("Cancel"),
-                    CreateButton::new("have").style(ButtonStyle::Primary).label("Already Have It"),
-                ]),
+                CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new("select", CreateSelectMenuKind::String {
+                        options: Vec::from_iter(cosmetics.iter().map(|(cosmetic, _)| {
+                            CreateSelectMenuOption::new(cosmetic, cosmetic).default_selection(selected.contains(cosmetic))
+                        }))
+                    })
+                    .placeholder("Select cosmetics to remove")
+                    .min_values(0)
+                    .max_values(cosmetics.len() as u8)
+                ),
+                CreateActionRow::Buttons(buttons),
             ])
-    ).await.unwrap();
+    }
+
+    fn export(&self) -> String {
+        self.needs.iter().fold(String::new(), |acc, (category, cosmetics)| {
+            let cosmetics = cosmetics.iter().fold(String::new(), |acc, (cosmetic, record)| format!("{}{} ({})\n", acc, cosmetic, record.describe_age()));
+            format!("{}# {}\n{}\n", acc, category, cosmetics)
+        })
+    }
+}
 
+#[poise::command(slash_command)]
+async fn whatdoineed(
+    ctx: Context<'_>,
+    #[description = "Only show cosmetics in this category"]
+    category: Option<CosmeticCategory>,
+) -> Result<(), Error> {
 
-    // wait for and parse the claim response
-    let message = claim_reply.message().await.unwrap();
 
-    let claimed_user = loop {
-        if let Some(interaction) = message.await_component_interaction(&ctx.serenity_context().shard).timeout(CLAIM_TIMEOUT).await {
-            interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
+    let user_id = ctx.author().id.to_string();
+    let database = &ctx.data().database;
 
-            let ComponentInteractionDataKind::Button = interaction.data.kind else {
-                println!("malformed component response. expected a `Button`, got {:?}", interaction.data.kind);
-                return Ok(());
-            };
+    let mut view = NeedsView::load(database, &user_id, category);
+
+    if view.total == 0 {
+        let content = match category {
+            Some(category) => format!("You don't need any **{}** cosmetics", category),
+            None => "You don't need anything".to_string(),
+        };
+
+        ctx.send(
+            CreateReply::default()
+                .content(content)
+                .ephemeral(true)
+        ).await.unwrap();
+
+        return Ok(());
+    }
+
+
+    let mut current_page = 0;
+    let mut selected = HashSet::new();
+
+    // the last removed cosmetics, whether they were marked as owned, and when they can't be undone anymore
+    let mut undo: Option<(RemovedNeeds, bool, std::time::Instant)> = None;
+
+    let reply = ctx.send(view.page(current_page, &selected, false)).await.unwrap();
+    let message = reply.message().await.unwrap();
+
+    loop {
+        let timeout = match &undo {
+            Some((_, _, expires)) => expires.saturating_duration_since(std::time::Instant::now()),
+            None => guild_settings(ctx).select_timeout,
+        };
+
+        let Some(interaction) = message.await_component_interaction(&ctx.serenity_context().shard).timeout(timeout).await else {
+            // the undo window has closed but the view is still open
+            if undo.take().is_some() {
+                reply.edit(ctx, view.page(current_page, &selected, false).content("")).await.unwrap();
+                continue;
+            }
+
+            break;
+        };
+
+        interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
 
-            let id = interaction.data.custom_id.as_str();
-
-            match id {
-                "cancel" => {
-                    if &interaction.user == ctx.author() {
-                        // cancel the share
-                        claim_reply.delete(ctx).await.unwrap();
-                        status_reply.edit(ctx, CreateReply::default().content(format!("<@{}> found **{}** but cancelled", author_id, cosmetic))).await.unwrap();
-                        return Ok(());
-                    } else {
-                        // dm the unauthorized user
-                        interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
-                        interaction.user.dm(ctx, CreateMessage::new().content("Only the creator of the cosmetic share can cancel it")).await.unwrap();
+        let mut content = String::new();
+
+        match (&interaction.data.kind, interaction.data.custom_id.as_str()) {
+            (ComponentInteractionDataKind::StringSelect { values }, "select") => {
+                // only the cosmetics on this page are in the menu
+                if let Some((_, cosmetics)) = view.pages.get(current_page) {
+                    for (cosmetic, _) in cosmetics {
+                        selected.remove(cosmetic);
                     }
-                },
-                "claim" => {
-                    break interaction.user;
-                },
-                "have" => {
-                    ctx.data().database.remove(&cosmetic, &interaction.user.id.to_string());
-                },
-                _ => {
-                    println!("malformed component response. invalid button id \"{}\"", id);
-                    return Ok(());
                 }
+
+                selected.extend(values.iter().cloned());
+            },
+            (ComponentInteractionDataKind::Button, "next") => {
+                current_page = (current_page + 1) % view.pages.len();
+            },
+            (ComponentInteractionDataKind::Button, "back") => {
+                current_page = (current_page + view.pages.len() - 1) % view.pages.len();
+            },
+            (ComponentInteractionDataKind::Button, id @ ("remove" | "owned")) => {
+                let owned = id == "owned";
+
+                // the whole records are kept so undoing keeps how long they've been needed
+                let removed = Vec::from_iter(
+                    selected.drain()
+                    .filter_map(|cosmetic| Some((database.need_record(&user_id, &cosmetic)?, cosmetic)))
+                );
+
+                for (_, cosmetic) in removed.iter() {
+                    database.remove(cosmetic, &user_id);
+
+                    if owned {
+                        database.add_owned(cosmetic, &user_id);
+                    }
+                }
+
+                content = format!("{} {} cosmetics", if owned { "Marked as owned" } else { "Removed" }, removed.len());
+
+                undo = Some((removed, owned, std::time::Instant::now() + UNDO_WINDOW));
+            },
+            (ComponentInteractionDataKind::Button, "undo") => {
+                if let Some((removed, owned, _)) = undo.take() {
+                    for (record, cosmetic) in removed.iter() {
+                        if !database.needs(&user_id, cosmetic) {
+                            database.add_record(cosmetic, record);
+                        }
+
+                        if owned {
+                            database.remove_owned(cosmetic, &user_id);
+                        }
+                    }
+
+                    content = format!("Put back {} cosmetics", removed.len());
+                }
+            },
+            (ComponentInteractionDataKind::Button, "export") => {
+                ctx.send(
+                    CreateReply::default()
+                        .content(format!("You need {} cosmetics", view.total))
+                        .attachment(CreateAttachment::bytes(view.export().into_bytes(), "needs.txt"))
+                        .ephemeral(true)
+                ).await.unwrap();
+            },
+            (_, id) => {
+                println!("malformed component response. invalid component id \"{}\"", id);
+                return Ok(());
             }
-        } else {
-            claim_reply.delete(ctx).await.unwrap();
-            status_reply.edit(ctx, CreateReply::default().content(format!("<@{}> found **{}** but no one responded within {:#?}", author_id, cosmetic, CLAIM_TIMEOUT))).await.unwrap();
-            return Ok(());
         }
-    };
 
+        // the needs might have changed so the pages need to be rebuilt
+        view = NeedsView::load(database, &user_id, category);
+        current_page = current_page.min(view.pages.len().saturating_sub(1));
 
-    // update the status and ping the user that they have claimed it
-    claim_reply.delete(ctx).await.unwrap();
-    status_reply.edit(ctx, CreateReply::default().content(format!("<@{}> found **{}** which has been claimed by <@{}>\n\nMake sure to use the `/dontneed` command later so you don't get pinged again", author_id, cosmetic, claimed_user.id))).await.unwrap();
+        reply.edit(ctx, view.page(current_page, &selected, undo.is_some()).content(content)).await.unwrap();
+    }
+
+    // stop the buttons from showing after they stop working
+    reply.edit(ctx, view.page(current_page, &selected, false).components(Vec::new())).await.unwrap();
 
 
     Ok(())
@@ -175,13 +454,14 @@ async fn foundsomething(
 
 
 #[poise::command(slash_command)]
-async fn needsomething(
+async fn dontneed(
     ctx: Context<'_>,
 ) -> Result<(), Error> {
 
+
     let status_reply = ctx.send(
         CreateReply::default()
-            .content(format!("Select the cosmetic you need"))
+            .content("Select the cosmetic you don't need")
             .ephemeral(true)
     ).await.unwrap();
 
@@ -193,15 +473,15 @@ async fn needsomething(
     };
 
 
-    if ctx.data().database.who_needs(&cosmetic).contains(&ctx.author().id.to_string()) {
-        status_reply.edit(ctx, CreateReply::default().content(format!("you already need **{}**", cosmetic))).await.unwrap();
-        return Ok(())
-    }
+    let user_id = &ctx.author().id.to_string();
 
+    if ctx.data().database.needs(user_id, &cosmetic) {
+        ctx.data().database.remove(&cosmetic, user_id);
 
-    // add to database
-    ctx.data().database.add(&cosmetic, &ctx.author().id.to_string());
-    status_reply.edit(ctx, CreateReply::default().content(format!("you now need **{}**", cosmetic))).await.unwrap();
+        status_reply.edit(ctx, CreateReply::default().content(format!("You now don't need **{}**", cosmetic))).await.unwrap();
+    } else {
+        status_reply.edit(ctx, CreateReply::default().content(format!("You already didn't need **{}**", cosmetic))).await.unwrap();
+    }
 
 
     Ok(())
@@ -210,26 +490,38 @@ async fn needsomething(
 
 
 #[poise::command(slash_command)]
-async fn whatdoineed(
+async fn gotspare(
     ctx: Context<'_>,
 ) -> Result<(), Error> {
 
+    let status_reply = ctx.send(
+        CreateReply::default()
+            .content("Select the cosmetic you have a spare of")
+            .ephemeral(true)
+    ).await.unwrap();
 
-    let cosmetics = ctx.data().database.needed_by(&ctx.author().id.to_string());
-
-    let content = if cosmetics.len() > 0 {
-        let content = cosmetics.iter().fold(String::new(), |acc, user| format!("{}\n**{}**", acc, user));
 
-        format!("You need\n{}", content)
-    } else {
-        "You don't need anything".to_string()
+    // get the cosmetic
+    let Some(cosmetic) = cosmetic_select(ctx).await else {
+        status_reply.edit(ctx, CreateReply::default().content("Cancelled")).await.unwrap();
+        return Ok(());
     };
 
-    ctx.send(
-        CreateReply::default()
-            .content(content)
-            .ephemeral(true)
-    ).await.unwrap();
+
+    let user_id = &ctx.author().id.to_string();
+
+    if ctx.data().database.has_spare(user_id, &cosmetic) {
+        status_reply.edit(ctx, CreateReply::default().content(format!("you already have a spare **{}**", cosmetic))).await.unwrap();
+        return Ok(())
+    }
+
+
+    // add to database
+    ctx.data().database.add_spare(&cosmetic, user_id);
+    status_reply.edit(ctx, CreateReply::default().content(format!("you now have a spare **{}**", cosmetic))).await.unwrap();
+
+
+    trades::propose_trades(ctx.serenity_context(), ctx.data(), user_id).await;
 
 
     Ok(())
@@ -238,14 +530,14 @@ async fn whatdoineed(
 
 
 #[poise::command(slash_command)]
-async fn dontneed(
+async fn nospare(
     ctx: Context<'_>,
 ) -> Result<(), Error> {
 
 
     let status_reply = ctx.send(
         CreateReply::default()
-            .content("Select the cosmetic you don't need")
+            .content("Select the cosmetic you don't have a spare of anymore")
             .ephemeral(true)
     ).await.unwrap();
 
@@ -259,12 +551,12 @@ async fn dontneed(
 
     let user_id = &ctx.author().id.to_string();
 
-    if ctx.data().database.needs(user_id, &cosmetic) {
-        ctx.data().database.remove(&cosmetic, user_id);
+    if ctx.data().database.has_spare(user_id, &cosmetic) {
+        ctx.data().database.remove_spare(&cosmetic, user_id);
 
-        status_reply.edit(ctx, CreateReply::default().content(format!("You now don't need **{}**", cosmetic))).await.unwrap();
+        status_reply.edit(ctx, CreateReply::default().content(format!("You now don't have a spare **{}**", cosmetic))).await.unwrap();
     } else {
-        status_reply.edit(ctx, CreateReply::default().content(format!("You already didn't need **{}**", cosmetic))).await.unwrap();
+        status_reply.edit(ctx, CreateReply::default().content(format!("You already didn't have a spare **{}**", cosmetic))).await.unwrap();
     }
 
 
@@ -273,23 +565,27 @@ async fn dontneed(
 
 
 
-const HELP_MESSAGE: &str = "This is a discord bot for sharing cosmetics with the community.
-
-You can tell it what cosmetics you need with `/needsomething`, and when you or someone finds a duplicate they can use the `/foundsomething` command to ping everyone that needs it.
-Use `/whatdoineed` to see what the bot thinks you need and `/dontneed` to tell it what you've unlocked.
-
-The bot keeps a shared database across all the servers it's in, but be aware that this means that users you don't share a server with might see your user.
-use `/forgetme` to remove your user from the databse.";
-
 #[poise::command(slash_command)]
-async fn help(
+async fn trades(
     ctx: Context<'_>,
 ) -> Result<(), Error> {
 
 
+    let user_id = ctx.author().id.to_string();
+
+    let trades = trades::find_trades(&ctx.data().database, &user_id);
+
+    let content = if !trades.is_empty() {
+        let content = trades.iter().fold(String::new(), |acc, trade| format!("{}\n\n{}", acc, trade.describe_for(&user_id)));
+
+        format!("You could make these trades{}", content)
+    } else {
+        "There aren't any trades you can make, use `/gotspare` to add your spares".to_string()
+    };
+
     ctx.send(
         CreateReply::default()
-            .content(HELP_MESSAGE)
+            .content(content)
             .ephemeral(true)
     ).await.unwrap();
 
@@ -298,469 +594,1554 @@ async fn help(
 }
 
 
+
+async fn autocomplete_cosmetic<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    ctx.data().database.search(partial).take(25).cloned()
+}
+
+/// how many members are fetched from discord at once when they aren't cached
+pub const MEMBER_LOOKUPS: usize = 10;
+
+/// discord's error code for a user that isn't in a server
+pub const UNKNOWN_MEMBER: isize = 10007;
+
 #[poise::command(slash_command)]
-async fn forgetme(
+async fn whoneeds(
     ctx: Context<'_>,
+    #[description = "The cosmetic to look up"]
+    #[autocomplete = "autocomplete_cosmetic"]
+    cosmetic: String,
 ) -> Result<(), Error> {
 
 
-    let reply = ctx.send(
-        CreateReply::default()
-            .content("This will make the bot forget all the cosmetics you need and remove you from it's database in *all* servers. This is **irreversible**, if you've spent lots of time entering in cosmetics you'll lose that progress.")
-            .ephemeral(true)
-            .components(vec![
-                CreateActionRow::Buttons(vec![
-                    CreateButton::new("yes").style(ButtonStyle::Danger).label("Yes, Do It"),
-                    CreateButton::new("no").style(ButtonStyle::Primary).label("Yeah... nevermind"),
-                ])
-            ]),
-    ).await.unwrap();
+    if !ctx.data().database.is_cosmetic(&cosmetic) {
+        ctx.send(
+            CreateReply::default()
+                .content(format!("**{}** isn't a cosmetic", cosmetic))
+                .ephemeral(true)
+        ).await.unwrap();
+        return Ok(());
+    }
 
 
-    // wait for button press
-    let message = reply.message().await.unwrap();
+    // looking up members can take longer than discord waits for a response
+    ctx.defer_ephemeral().await?;
 
-    let interaction = match message.await_component_interaction(&ctx.serenity_context().shard).timeout(Duration::from_secs(60)).await {
-        Some(interaction) => interaction,
-        None => {
-            reply.delete(ctx).await.unwrap();
-            ctx.send(CreateReply::default().ephemeral(true).content("Timed out")).await.unwrap();
-            return Ok(());
-        },
-    };
+    let needed_users = ctx.data().database.who_needs(&cosmetic);
 
-    interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
+    let scope = guild_settings(ctx).privacy_scope;
 
-    let ComponentInteractionDataKind::Button = interaction.data.kind else {
-        println!("malformed component response. expected a `Button`, got {:?}", interaction.data.kind);
-        return Ok(());
+    let mut content = match (scope, ctx.guild_id()) {
+        (PrivacyScope::Server, Some(_)) => format!("**{}** is needed by", cosmetic),
+        _ => format!("**{}** is needed by **{}** users across all servers", cosmetic, needed_users.len()),
     };
 
-    let id = interaction.data.custom_id.as_str();
 
-    match id {
-        "yes" => {
-            ctx.data().database.forget(&interaction.user.id.to_string());
+    // only users that share this server are counted or named
+    if let Some(guild_id) = ctx.guild_id() {
+        let mut guild_count = 0;
+        let mut visible = Vec::new();
 
-            ctx.send(
-                CreateReply::default()
-                    .content("You've been deleted")
-                    .ephemeral(true)
-            ).await.unwrap();
-        },
-        "no" => {
-            ctx.send(
-                CreateReply::default()
-                    .content("Cancelled")
-                    .ephemeral(true)
-            ).await.unwrap();
-        }
-        _ => {
-            println!("malformed component response. invalid button id \"{}\"", id);
-            return Ok(());
-        }
-    }
+        // members are taken from the cache when they're there, and the rest are fetched a few at a time
+        let members: Vec<(String, Option<String>)> = serenity::futures::stream::iter(needed_users.clone())
+            .map(|user_id| async move {
+                let Ok(id) = user_id.parse::<u64>() else {
+                    return (user_id, None);
+                };
+                let id = serenity::all::UserId::new(id);
 
+                let cached = ctx.cache().guild(guild_id).and_then(|guild| guild.members.get(&id).map(|member| member.display_name().to_string()));
 
-    Ok(())
-}
+                let name = match cached {
+                    Some(name) => Some(name),
+                    None => guild_id.member(ctx, id).await.ok().map(|member| member.display_name().to_string()),
+                };
 
+                (user_id, name)
+            })
+            .buffered(MEMBER_LOOKUPS)
+            .collect()
+            .await;
 
-async fn cosmetic_select(
-    ctx: Context<'_>,
-) -> Option<String> {
+        for (user_id, name) in members {
+            let Some(name) = name else {
+                continue;
+            };
 
+            guild_count += 1;
 
-    // prompt to the author for the category of the cosmetic
-    let category_reply = ctx.send(
-        CreateReply::default()
-        .content("Select category")
-        .ephemeral(true)
-        .components(vec![
-            CreateActionRow::SelectMenu(
-                CreateSelectMenu::new(
-                    "category",
-                    CreateSelectMenuKind::String {
-                        options: Vec::from_iter(CosmeticCategory::ALL.into_iter().map(
-                            |c| CreateSelectMenuOption::new(format!("{}", c), c.to_id())
-                        ))
-                    }
-                )
-            ),
-            CreateActionRow::Buttons(vec![
-                CreateButton::new("cancel").label("Cancel").style(ButtonStyle::Danger),
-            ]),
-        ])
+            if ctx.data().database.user_settings(&user_id).visible {
+                visible.push(name);
+            }
+        }
+
+        content = match scope {
+            PrivacyScope::Server => format!("{} **{}** users in this server", content, guild_count),
+            PrivacyScope::Global => format!("{}, and **{}** in this server", content, guild_count),
+        };
+
+        // names stop before the message gets too long for discord, leaving room for the lines around them
+        let mut shown = 0;
+        for name in visible.iter() {
+            if content.len() + name.len() + 200 > 2000 {
+                break;
+            }
+
+            content = format!("{}\n{}", content, name);
+            shown += 1;
+        }
+
+        if shown < visible.len() {
+            content = format!("{}\n...and {} more", content, visible.len() - shown);
+        }
+
+        if guild_count > visible.len() {
+            content = format!("{}\n\n{} users in this server chose to stay hidden", content, guild_count - visible.len());
+        }
+    }
+
+
+    ctx.send(
+        CreateReply::default()
+            .content(content)
+            .ephemeral(true)
     ).await.unwrap();
 
 
-    // wait for and parse the response from the prompt
-    let message = category_reply.message().await.unwrap();
+    Ok(())
+}
 
-    let interaction = match message.await_component_interaction(&ctx.serenity_context().shard).timeout(Duration::from_secs(60)).await {
-        Some(interaction) => interaction,
-        None => {
-            category_reply.delete(ctx).await.unwrap();
-            ctx.send(CreateReply::default().ephemeral(true).content("Timed out")).await.unwrap();
-            return None;
-        },
-    };
 
-    let values = match &interaction.data.kind {
-        ComponentInteractionDataKind::StringSelect { values } => values,
-        ComponentInteractionDataKind::Button => {
-            category_reply.delete(ctx).await.unwrap();
-            return None;
-        },
-        _ => {
-            println!("malformed component response. expected a `StringSelect` or `Button`, got {:?}", interaction.data.kind);
-            return None;
-        },
-    };
 
-    let Some(id) = values.first() else {
-        println!("malformed component response, there was no selected value");
-        return None;
+#[poise::command(slash_command)]
+async fn visibility(
+    ctx: Context<'_>,
+    #[description = "Whether people in your servers can see your name in /whoneeds"]
+    visible: bool,
+) -> Result<(), Error> {
+
+
+    let user_id = ctx.author().id.to_string();
+
+    let mut settings = ctx.data().database.user_settings(&user_id);
+    settings.visible = visible;
+    ctx.data().database.set_user_settings(&user_id, &settings);
+
+    let content = if visible {
+        "People in your servers can now see your name in `/whoneeds`"
+    } else {
+        "You're now hidden in `/whoneeds`, people will only see how many users need something"
     };
 
-    let Some(category) = CosmeticCategory::from_id(id) else {
-        println!("malformed component response, invalid cosmetic category id \"{}\"", id);
-        return None;
+    ctx.send(
+        CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+    ).await.unwrap();
+
+
+    Ok(())
+}
+
+
+
+#[poise::command(slash_command)]
+async fn stats(
+    ctx: Context<'_>,
+    #[description = "Whether to count this server or every server, defaults to this server"]
+    scope: Option<stats::StatsScope>,
+) -> Result<(), Error> {
+
+
+    // servers can keep stats to their own members
+    let guild_id = match scope {
+        Some(stats::StatsScope::Global) if guild_settings(ctx).privacy_scope == PrivacyScope::Global => None,
+        _ => ctx.guild_id(),
     };
 
+    // collecting can take a while with lots of users
+    ctx.defer().await?;
 
-    // acknowledge and delete the prompt
-    interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
-    category_reply.delete(ctx).await.unwrap();
+    let stats = stats::collect(&ctx.data().database, guild_id.map(|guild_id| (ctx.serenity_context(), guild_id))).await;
 
+    let title = if guild_id.is_some() {
+        "Stats for this server"
+    } else {
+        "Stats across all servers"
+    };
 
-    // can only have up to 25 in each list, so break into chunks of 25
-    let mut cosmetics = ctx.data().database.cosmetics_in_category(category);
-    let mut chunks = Vec::new();
+    let mut reply = CreateReply::default()
+        .content(format!("# {}\n{}", title, stats.describe()))
+        // don't ping the top finders
+        .allowed_mentions(CreateAllowedMentions::new());
 
-    loop {
-        let chunk = Vec::from_iter((&mut cosmetics).take(25));
-        if chunk.len() == 0 {
-            break;
-        }
-        chunks.push(chunk);
+    match charts::render_all(&stats) {
+        Ok(charts) => {
+            for (name, png) in charts {
+                reply = reply.attachment(CreateAttachment::bytes(png, name));
+            }
+        },
+        Err(error) => println!("could not render stats charts: {}", error),
     }
 
-    // can only have 4 chunks at a time so break into pages
-    let mut chunks = chunks.into_iter();
-    let mut pages = Vec::new();
+    ctx.send(reply).await.unwrap();
 
-    loop {
-        let page = Vec::from_iter((&mut chunks).take(4));
-        if page.len() == 0 {
-            break;
-        }
-        pages.push(page);
-    }
 
+    Ok(())
+}
 
-    let create_page = |n: usize| -> CreateReply {
 
-        let mut components = Vec::from_iter(
-            pages.get(n).unwrap().iter().enumerate().map(|(i, chunk)| {
-                CreateActionRow::SelectMenu(CreateSelectMenu::new(i.to_string(), CreateSelectMenuKind::String {
-                    options: Vec::from_iter(chunk.into_iter().map(|&cosmetic| {
-                        CreateSelectMenuOption::new(cosmetic, cosmetic)
-                    }))
-                }).placeholder(*chunk.first().unwrap()))
-            })
-        );
 
-        components.push(CreateActionRow::Buttons(vec![
-            CreateButton::new("back").label("< Page"),
-            CreateButton::new("next").label("Page >"),
-            CreateButton::new("cancel").label("Cancel").style(ButtonStyle::Danger),
-        ]));
+const HELP_MESSAGE: &str = "This is a discord bot for sharing cosmetics with the community.
+
+You can tell it what cosmetics you need with `/needsomething` (give it a `priority` to get pinged first for the things you want most), and when you or someone finds a duplicate they can use the `/foundsomething` command to ping everyone that needs it. If you found a few copies or a few different cosmetics you can share them all at once, and `private_note` lets you leave a lobby code that only the people that get it will see.
+By default whoever claims it first gets it, but the finder can pick a `mode` to give it to a random interested user or whoever has needed it the longest instead.
+Once it's been handed over the finder or the claimant clicks \"Received\" so the claimant stops getting pinged for it, or \"Handoff Failed\" to pass it on to the next person. If the claimant doesn't show up, the finder can release their claim to ping everyone again.
+Use `/whatdoineed` to see what the bot thinks you need and `/dontneed` to tell it what you've unlocked, you can also select cosmetics in `/whatdoineed` to remove lots at once.
+If you have spares use `/gotspare` and `/nospare` to keep track of them, the bot will dm you when there's a swap you can make with other users, and `/trades` shows every trade you could make right now.
+
+The bot keeps a shared database across all the servers it's in, but be aware that this means that users you don't share a server with might see your user.
+If you leave every server the bot is in your needs are archived and your spares are forgotten, and `/unarchive` brings your needs back if you return.
+`/whoneeds` only shows how many people need something, unless you use `/visibility` to let people in your servers see your name.
+Use `/notifications` to get dms instead of pings for some categories, only hear about your higher priority needs, or mute shares completely.
+Use `/quiethours` to set your timezone and a time you don't want to be pinged, anything found then gets sent to you in one dm once it's over.
+If you're not around when things get found, `/digest` sends you a daily or weekly dm of what you missed and who has spares of it.
+`/inbox` lists the latest shares of things you need, with a button to claim the ones that are still open.
+Taking a break? `/snooze` stops your pings and trade offers for a while, and `/vacation` is for longer breaks and holds back your digest too, without losing your needs.
+If you haven't used the bot in a while you'll get a dm asking if you still need things, and if you don't answer they're archived until you use `/unarchive`. `/notifications` can change how long that takes.
+use `/forgetme` to remove your user from the databse, it's kept for 7 days in case you change your mind and `/restoreme` brings it back.
+
+Server admins can use `/config` to pick where shares are posted, how people get pinged, how long shares and menus stay open, and who can use the bot.";
+
+#[poise::command(slash_command)]
+async fn help(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
 
+
+    ctx.send(
         CreateReply::default()
-        .content(format!("Select cosmetic\nPage **{}**/{}", n + 1, pages.len()))
-        .ephemeral(true)
-        .components(components)
+            .content(HELP_MESSAGE)
+            .ephemeral(true)
+    ).await.unwrap();
+
+
+    Ok(())
+}
+
+
+/// how long forgotten users have to change their mind, unless they pick their own
+const FORGET_GRACE_DAYS: u64 = 7;
+
+#[poise::command(slash_command)]
+async fn forgetme(
+    ctx: Context<'_>,
+    #[description = "Days to keep your data in case you change your mind, defaults to 7, 0 deletes it straight away"]
+    #[min = 0]
+    #[max = 30]
+    grace_period: Option<u64>,
+) -> Result<(), Error> {
+
+
+    let grace_period = grace_period.unwrap_or(FORGET_GRACE_DAYS);
+
+    let warning = match grace_period {
+        0 => "This is **irreversible**, if you've spent lots of time entering in cosmetics you'll lose that progress.".to_string(),
+        days => format!("Your data is kept for **{} days** in case you change your mind, use `/restoreme` to get it back before then. After that it's gone for good.", days),
     };
 
+    let reply = ctx.send(
+        CreateReply::default()
+            .content(format!("This will make the bot forget all the cosmetics you need and remove you from it's database in *all* servers. {}", warning))
+            .ephemeral(true)
+            .components(vec![
+                CreateActionRow::Buttons(vec![
+                    CreateButton::new("yes").style(ButtonStyle::Danger).label("Yes, Do It"),
+                    CreateButton::new("no").style(ButtonStyle::Primary).label("Yeah... nevermind"),
+                ])
+            ]),
+    ).await.unwrap();
 
-    let mut current_page = 0;
 
-    // prompt the author for the cosmetic in the category
-    let cosmetic_reply = ctx.send(create_page(current_page)).await.unwrap();
+    // wait for button press
+    let message = reply.message().await.unwrap();
 
-    // wait for and parse the response from the prompt
-    let message = cosmetic_reply.message().await.unwrap();
+    let interaction = match message.await_component_interaction(&ctx.serenity_context().shard).timeout(guild_settings(ctx).select_timeout).await {
+        Some(interaction) => interaction,
+        None => {
+            reply.delete(ctx).await.unwrap();
+            ctx.send(CreateReply::default().ephemeral(true).content("Timed out")).await.unwrap();
+            return Ok(());
+        },
+    };
 
-    let cosmetic = loop {
-        let interaction = match message.await_component_interaction(&ctx.serenity_context().shard).timeout(Duration::from_secs(60)).await {
-            Some(interaction) => interaction,
-            None => {
-                cosmetic_reply.delete(ctx).await.unwrap();
-                ctx.send(CreateReply::default().ephemeral(true).content("Timed out")).await.unwrap();
-                return None;
-            },
-        };
+    interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
 
-        interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
+    let ComponentInteractionDataKind::Button = interaction.data.kind else {
+        println!("malformed component response. expected a `Button`, got {:?}", interaction.data.kind);
+        return Ok(());
+    };
 
-        match interaction.data.kind {
-            ComponentInteractionDataKind::StringSelect { values } => {
-                let Some(cosmetic) = values.first() else {
-                    println!("malformed component response, there was no selected value");
-                    return None;
-                };
+    let id = interaction.data.custom_id.as_str();
 
-                break cosmetic.clone();
-            },
+    match id {
+        "yes" => {
+            let user_id = interaction.user.id.to_string();
 
-            ComponentInteractionDataKind::Button => {
-                match interaction.data.custom_id.as_str() {
-                    "next" => {
-                        if current_page >= pages.len() - 1 {
-                            current_page = 0;
-                        } else {
-                            current_page += 1;
-                        }
+            let content = match grace_period {
+                0 => {
+                    ctx.data().database.forget(&user_id);
+                    "You've been deleted".to_string()
+                },
+                days => {
+                    let purge_at = now() + days * 60 * 60 * 24;
+                    ctx.data().database.tombstone(&user_id, purge_at);
+                    format!("You've been deleted, use `/restoreme` before <t:{}:f> if you want your data back", purge_at)
+                },
+            };
 
-                        cosmetic_reply.edit(ctx, create_page(current_page)).await.unwrap();
-                    },
-                    "back" => {
-                        if current_page == 0 {
-                            current_page = pages.len() - 1;
-                        } else {
-                            current_page -= 1;
-                        }
+            ctx.send(
+                CreateReply::default()
+                    .content(content)
+                    .ephemeral(true)
+            ).await.unwrap();
+        },
+        "no" => {
+            ctx.send(
+                CreateReply::default()
+                    .content("Cancelled")
+                    .ephemeral(true)
+            ).await.unwrap();
+        }
+        _ => {
+            println!("malformed component response. invalid button id \"{}\"", id);
+            return Ok(());
+        }
+    }
 
-                        cosmetic_reply.edit(ctx, create_page(current_page)).await.unwrap();
-                    },
-                    "cancel" => {
-                        cosmetic_reply.delete(ctx).await.unwrap();
-                        return None;
-                    }
-                    _ => {
-                        println!("malformed component response");
-                        return None;
-                    }
-                }
-            },
 
-            _ => {
-                println!("malformed component response");
-                return None;
+    Ok(())
+}
+
+
+#[poise::command(slash_command)]
+async fn restoreme(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+
+
+    let content = match ctx.data().database.restore_tombstone(&ctx.author().id.to_string()) {
+        true => "Welcome back, everything the bot forgot about you has been restored",
+        false => "There's nothing to restore, either you haven't used `/forgetme` or your data has already been deleted for good",
+    };
+
+    ctx.send(
+        CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+    ).await.unwrap();
+
+
+    Ok(())
+}
+
+
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD", required_permissions = "MANAGE_GUILD")]
+#[allow(clippy::too_many_arguments)]
+async fn config(
+    ctx: Context<'_>,
+    #[description = "Where the bot posts shares, instead of wherever /foundsomething is used"]
+    #[channel_types("Text")]
+    announcement_channel: Option<serenity::all::GuildChannel>,
+    #[description = "Post shares wherever /foundsomething is used again"]
+    clear_announcement_channel: Option<bool>,
+    #[description = "Whether claimed shares get a thread for the finder and claimants to sort out the handoffs"]
+    share_threads: Option<bool>,
+    #[description = "How the person that gets a share is picked when the finder doesn't say"]
+    claim_mode: Option<ClaimMode>,
+    #[description = "How many minutes people have to claim a share"]
+    #[min = 1] #[max = 1440]
+    claim_timeout: Option<u64>,
+    #[description = "The most minutes a finder can give people to claim with the duration option"]
+    #[min = 1] #[max = 1440]
+    max_claim_timeout: Option<u64>,
+    #[description = "How many seconds menus and buttons wait for a response"]
+    #[min = 15] #[max = 600]
+    select_timeout: Option<u64>,
+    #[description = "How users that need a shared cosmetic are told about it"]
+    ping_style: Option<PingStyle>,
+    #[description = "Whether the bot shows counts from other servers here"]
+    privacy_scope: Option<PrivacyScope>,
+    #[description = "Let members with this role use the bot, once any role is added only those members can"]
+    allow_role: Option<serenity::all::Role>,
+    #[description = "Stop letting members with this role use the bot"]
+    disallow_role: Option<serenity::all::Role>,
+    #[description = "The language code the bot replies in, like en or en-US"]
+    locale: Option<String>,
+) -> Result<(), Error> {
+
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let mut settings = ctx.data().guild_settings.get(guild_id);
+
+    if let Some(channel) = announcement_channel {
+        if channel.guild_id != guild_id {
+            ctx.send(CreateReply::default().content("The announcement channel has to be in this server").ephemeral(true)).await.unwrap();
+            return Ok(());
+        }
+
+        settings.announcement_channel = Some(channel.id);
+    }
+
+    if clear_announcement_channel == Some(true) {
+        settings.announcement_channel = None;
+    }
+
+    if let Some(share_threads) = share_threads {
+        settings.share_threads = share_threads;
+    }
+
+    if let Some(claim_mode) = claim_mode {
+        settings.claim_mode = claim_mode;
+    }
+
+    if let Some(claim_timeout) = claim_timeout {
+        settings.claim_timeout = Duration::from_secs(claim_timeout * 60);
+    }
+
+    if let Some(max_claim_timeout) = max_claim_timeout {
+        settings.max_claim_timeout = Duration::from_secs(max_claim_timeout * 60);
+    }
+
+    if let Some(select_timeout) = select_timeout {
+        settings.select_timeout = Duration::from_secs(select_timeout);
+    }
+
+    if let Some(ping_style) = ping_style {
+        settings.ping_style = ping_style;
+    }
+
+    if let Some(privacy_scope) = privacy_scope {
+        settings.privacy_scope = privacy_scope;
+    }
+
+    if let Some(role) = allow_role {
+        if !settings.allowed_roles.contains(&role.id) {
+            settings.allowed_roles.push(role.id);
+        }
+    }
+
+    if let Some(role) = disallow_role {
+        settings.allowed_roles.retain(|id| *id != role.id);
+    }
+
+    if let Some(locale) = locale {
+        settings.locale = locale;
+    }
+
+    if let Err(problem) = settings.validate() {
+        ctx.send(CreateReply::default().content(problem).ephemeral(true)).await.unwrap();
+        return Ok(());
+    }
+
+    ctx.data().guild_settings.set(guild_id, settings.clone());
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!("# Settings for this server\n{}", settings.describe()))
+            .allowed_mentions(CreateAllowedMentions::new())
+            .ephemeral(true)
+    ).await.unwrap();
+
+
+    Ok(())
+}
+
+
+/// once a server has allowed roles only members with one of them, or that can manage the server, can use the bot
+async fn has_allowed_role(ctx: Context<'_>) -> Result<bool, Error> {
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(true);
+    };
+
+    let settings = ctx.data().guild_settings.get(guild_id);
+
+    if settings.allowed_roles.is_empty() {
+        return Ok(true);
+    }
+
+    let Some(member) = ctx.author_member().await else {
+        return Ok(false);
+    };
+
+    let can_manage = member.permissions.is_some_and(|permissions| permissions.manage_guild());
+
+    if can_manage || member.roles.iter().any(|role| settings.allowed_roles.contains(role)) {
+        return Ok(true);
+    }
+
+    ctx.send(
+        CreateReply::default()
+            .content("You don't have a role that's allowed to use the bot in this server")
+            .ephemeral(true)
+    ).await.unwrap();
+
+    Ok(false)
+}
+
+
+pub async fn cosmetic_select(
+    ctx: Context<'_>,
+) -> Option<String> {
+
+
+    // prompt to the author for the category of the cosmetic
+    let category_reply = ctx.send(
+        CreateReply::default()
+        .content("Select category")
+        .ephemeral(true)
+        .components(vec![
+            CreateActionRow::SelectMenu(
+                CreateSelectMenu::new(
+                    "category",
+                    CreateSelectMenuKind::String {
+                        options: Vec::from_iter(CosmeticCategory::ALL.iter().map(
+                            |c| CreateSelectMenuOption::new(format!("{}", c), c.to_id())
+                        ))
+                    }
+                )
+            ),
+            CreateActionRow::Buttons(vec![
+                CreateButton::new("cancel").label("Cancel").style(ButtonStyle::Danger),
+            ]),
+        ])
+    ).await.unwrap();
+
+
+    // wait for and parse the response from the prompt
+    let message = category_reply.message().await.unwrap();
+
+    let interaction = match message.await_component_interaction(&ctx.serenity_context().shard).timeout(guild_settings(ctx).select_timeout).await {
+        Some(interaction) => interaction,
+        None => {
+            category_reply.delete(ctx).await.unwrap();
+            ctx.send(CreateReply::default().ephemeral(true).content("Timed out")).await.unwrap();
+            return None;
+        },
+    };
+
+    let values = match &interaction.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => values,
+        ComponentInteractionDataKind::Button => {
+            category_reply.delete(ctx).await.unwrap();
+            return None;
+        },
+        _ => {
+            println!("malformed component response. expected a `StringSelect` or `Button`, got {:?}", interaction.data.kind);
+            return None;
+        },
+    };
+
+    let Some(id) = values.first() else {
+        println!("malformed component response, there was no selected value");
+        return None;
+    };
+
+    let Some(category) = CosmeticCategory::from_id(id) else {
+        println!("malformed component response, invalid cosmetic category id \"{}\"", id);
+        return None;
+    };
+
+
+    // acknowledge and delete the prompt
+    interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
+    category_reply.delete(ctx).await.unwrap();
+
+
+    // can only have up to 25 in each list, so break into chunks of 25
+    let mut cosmetics = ctx.data().database.cosmetics_in_category(category);
+    let mut chunks = Vec::new();
+
+    loop {
+        let chunk = Vec::from_iter((&mut cosmetics).take(25));
+        if chunk.is_empty() {
+            break;
+        }
+        chunks.push(chunk);
+    }
+
+    // can only have 4 chunks at a time so break into pages
+    let mut chunks = chunks.into_iter();
+    let mut pages = Vec::new();
+
+    loop {
+        let page = Vec::from_iter((&mut chunks).take(4));
+        if page.is_empty() {
+            break;
+        }
+        pages.push(page);
+    }
+
+
+    let create_page = |n: usize| -> CreateReply {
+
+        let mut components = Vec::from_iter(
+            pages.get(n).unwrap().iter().enumerate().map(|(i, chunk)| {
+                CreateActionRow::SelectMenu(CreateSelectMenu::new(i.to_string(), CreateSelectMenuKind::String {
+                    options: Vec::from_iter(chunk.iter().map(|&cosmetic| {
+                        CreateSelectMenuOption::new(cosmetic, cosmetic)
+                    }))
+                }).placeholder(*chunk.first().unwrap()))
+            })
+        );
+
+        components.push(CreateActionRow::Buttons(vec![
+            CreateButton::new("back").label("< Page"),
+            CreateButton::new("next").label("Page >"),
+            CreateButton::new("cancel").label("Cancel").style(ButtonStyle::Danger),
+        ]));
+
+        CreateReply::default()
+        .content(format!("Select cosmetic\nPage **{}**/{}", n + 1, pages.len()))
+        .ephemeral(true)
+        .components(components)
+    };
+
+
+    let mut current_page = 0;
+
+    // prompt the author for the cosmetic in the category
+    let cosmetic_reply = ctx.send(create_page(current_page)).await.unwrap();
+
+    // wait for and parse the response from the prompt
+    let message = cosmetic_reply.message().await.unwrap();
+
+    let cosmetic = loop {
+        let interaction = match message.await_component_interaction(&ctx.serenity_context().shard).timeout(guild_settings(ctx).select_timeout).await {
+            Some(interaction) => interaction,
+            None => {
+                cosmetic_reply.delete(ctx).await.unwrap();
+                ctx.send(CreateReply::default().ephemeral(true).content("Timed out")).await.unwrap();
+                return None;
+            },
+        };
+
+        interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
+
+        match interaction.data.kind {
+            ComponentInteractionDataKind::StringSelect { values } => {
+                let Some(cosmetic) = values.first() else {
+                    println!("malformed component response, there was no selected value");
+                    return None;
+                };
+
+                break cosmetic.clone();
+            },
+
+            ComponentInteractionDataKind::Button => {
+                match interaction.data.custom_id.as_str() {
+                    "next" => {
+                        if current_page >= pages.len() - 1 {
+                            current_page = 0;
+                        } else {
+                            current_page += 1;
+                        }
+
+                        cosmetic_reply.edit(ctx, create_page(current_page)).await.unwrap();
+                    },
+                    "back" => {
+                        if current_page == 0 {
+                            current_page = pages.len() - 1;
+                        } else {
+                            current_page -= 1;
+                        }
+
+                        cosmetic_reply.edit(ctx, create_page(current_page)).await.unwrap();
+                    },
+                    "cancel" => {
+                        cosmetic_reply.delete(ctx).await.unwrap();
+                        return None;
+                    }
+                    _ => {
+                        println!("malformed component response");
+                        return None;
+                    }
+                }
+            },
+
+            _ => {
+                println!("malformed component response");
+                return None;
+            }
+        }
+    };
+
+
+    cosmetic_reply.delete(ctx).await.unwrap();
+
+
+    Some(cosmetic.clone())
+}
+
+
+
+async fn handle_event(ctx: &serenity::all::Context, event: &serenity::all::FullEvent, data: &Data) -> Result<(), Error> {
+    membership::handle_event(ctx, event, data).await?;
+    inbox::handle_event(ctx, event, data).await?;
+
+    Ok(())
+}
+
+
+/// the settings of the server the command was used in, or the defaults in dms
+pub fn guild_settings(ctx: Context<'_>) -> GuildSettings {
+    ctx.guild_id().map(|guild_id| ctx.data().guild_settings.get(guild_id)).unwrap_or_default()
+}
+
+
+/// sends a message to a user's dms, which fails if they don't share a server with the bot or have dms turned off
+pub async fn dm_user(ctx: &serenity::all::Context, user_id: serenity::all::UserId, message: CreateMessage) -> serenity::Result<serenity::all::Message> {
+    user_id.create_dm_channel(ctx).await?.send_message(ctx, message).await
+}
+
+/// the most characters discord allows in a message
+pub const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// splits a message into pieces that fit in one discord message each, between lines where it can
+pub fn split_message(content: &str) -> Vec<String> {
+    let mut messages = vec![String::new()];
+
+    for line in content.split('\n') {
+        // lines too long for a message on their own are broken up wherever they hit the limit
+        let chars = Vec::from_iter(line.chars());
+
+        for piece in chars.chunks(MAX_MESSAGE_LENGTH).map(String::from_iter).chain(line.is_empty().then(String::new)) {
+            let current = messages.last_mut().unwrap();
+
+            if current.chars().count() + piece.chars().count() + 1 > MAX_MESSAGE_LENGTH {
+                messages.push(piece);
+            } else if current.is_empty() {
+                current.push_str(&piece);
+            } else {
+                current.push('\n');
+                current.push_str(&piece);
+            }
+        }
+    }
+
+    messages.retain(|message| !message.trim().is_empty());
+    messages
+}
+
+/// acknowledges a button press, which only fails when discord is having problems or the interaction is too old
+pub async fn acknowledge(ctx: impl serenity::all::CacheHttp, interaction: &serenity::all::ComponentInteraction) {
+    if let Err(error) = interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await {
+        println!("could not acknowledge an interaction: {}", error);
+    }
+}
+
+/// answers a button press with a message only the user that pressed it can see, since their dms might be closed
+pub async fn reply_privately(ctx: impl serenity::all::CacheHttp, interaction: &serenity::all::ComponentInteraction, content: impl Into<String>) {
+    let response = CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(content).ephemeral(true));
+
+    if let Err(error) = interaction.create_response(ctx, response).await {
+        println!("could not reply to an interaction: {}", error);
+    }
+}
+
+
+
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CosmeticCategory {
+    Hat,
+    Top,
+    Bottom,
+    Accessory,
+    Vest,
+    Belt,
+}
+
+impl CosmeticCategory {
+    pub const ALL: &'static [CosmeticCategory] = &[
+        Self::Hat,
+        Self::Top,
+        Self::Bottom,
+        Self::Accessory,
+        Self::Vest,
+        Self::Belt,
+    ];
+}
+
+impl std::fmt::Display for CosmeticCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Hat => "Hat",
+            Self::Top => "Top",
+            Self::Bottom => "Bottom",
+            Self::Accessory => "Accessory",
+            Self::Vest => "Vest",
+            Self::Belt => "Belt",
+        })
+    }
+}
+
+impl CosmeticCategory {
+    pub fn to_id(&self) -> &'static str {
+        match self {
+            Self::Hat => "0",
+            Self::Top => "1",
+            Self::Bottom => "2",
+            Self::Accessory => "3",
+            Self::Vest => "4",
+            Self::Belt => "5",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "0" => Some(Self::Hat),
+            "1" => Some(Self::Top),
+            "2" => Some(Self::Bottom),
+            "3" => Some(Self::Accessory),
+            "4" => Some(Self::Vest),
+            "5" => Some(Self::Belt),
+            _ => None,
+        }
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct Database {
+    hats: Vec<String>,
+    tops: Vec<String>,
+    bottoms: Vec<String>,
+    accessories: Vec<String>,
+    vests: Vec<String>,
+    belts: Vec<String>,
+
+    all: Vec<String>,
+}
+
+
+impl Database {
+    pub fn new() -> Database {
+
+        let mut database = Database::default();
+
+        for category in CosmeticCategory::ALL {
+
+            let path = format!("cosmetics/{}.txt", category);
+
+            let cosmetics = std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("could not read {:?}", path));
+
+            let cosmetics = filter_allowed_characters(cosmetics);
+
+            let mut cosmetics = Vec::from_iter(
+                cosmetics.split("\n")
+                .map(str::to_string)
+                .filter(|line| !line.is_empty())
+            );
+
+            for cosmetic in cosmetics.iter() {
+                database.all.push(cosmetic.clone());
+            }
+
+            cosmetics.sort();
+
+            *match category {
+                CosmeticCategory::Hat => &mut database.hats,
+                CosmeticCategory::Top => &mut database.tops,
+                CosmeticCategory::Bottom => &mut database.bottoms,
+                CosmeticCategory::Accessory => &mut database.accessories,
+                CosmeticCategory::Vest => &mut database.vests,
+                CosmeticCategory::Belt => &mut database.belts,
+            } = cosmetics;
+        }
+
+        database
+    }
+
+    /// gets the database files ready for the bot and tidies up after the last time it ran.
+    /// only the bot does this, so `export-charts` can read the database while the bot is running
+    pub fn prepare(&self) {
+        std::fs::create_dir_all("database/spares").expect("could not create the database directory");
+        std::fs::create_dir_all("database/users").expect("could not create the database directory");
+        std::fs::create_dir_all("database/owned").expect("could not create the database directory");
+        std::fs::create_dir_all("database/shares").expect("could not create the database directory");
+        std::fs::create_dir_all("database/guilds").expect("could not create the database directory");
+        std::fs::create_dir_all("database/queued").expect("could not create the database directory");
+        std::fs::create_dir_all("database/archived").expect("could not create the database directory");
+        std::fs::create_dir_all("database/tombstones").expect("could not create the database directory");
+        std::fs::create_dir_all("database/activity").expect("could not create the database directory");
+
+        self.migrate_need_records();
+        self.close_interrupted_shares();
+    }
+
+    pub fn category_of(&self, cosmetic: &str) -> Option<CosmeticCategory> {
+        CosmeticCategory::ALL.iter().copied().find(|category| self.cosmetics_in_category(*category).any(|c| c == cosmetic))
+    }
+
+    pub fn cosmetics_in_category(&self, category: CosmeticCategory) -> impl Iterator<Item = &String> + '_ {
+        match category {
+            CosmeticCategory::Hat => self.hats.iter(),
+            CosmeticCategory::Top => self.tops.iter(),
+            CosmeticCategory::Bottom => self.bottoms.iter(),
+            CosmeticCategory::Accessory => self.accessories.iter(),
+            CosmeticCategory::Vest => self.vests.iter(),
+            CosmeticCategory::Belt => self.belts.iter(),
+        }
+    }
+
+    pub fn needs(&self, user_id: &str, cosmetic: &str) -> bool {
+        self.need_record(user_id, cosmetic).is_some()
+    }
+
+    pub fn who_needs(&self, cosmetic: &str) -> Vec<String> {
+        Vec::from_iter(self.need_records(cosmetic).into_iter().map(|record| record.user_id))
+    }
+
+    /// everyone that needs a cosmetic, in the order they added it
+    pub fn need_records(&self, cosmetic: &str) -> Vec<NeedRecord> {
+        Vec::from_iter(read_users(&create_path(cosmetic)).iter().filter_map(|line| NeedRecord::parse(line)))
+    }
+
+    /// the need records of everyone that can be pinged about a cosmetic right now, leaving out snoozed users
+    pub fn pingable_need_records(&self, cosmetic: &str) -> Vec<NeedRecord> {
+        let snoozed = self.snoozed_users(now());
+        Vec::from_iter(self.need_records(cosmetic).into_iter().filter(|record| !snoozed.contains(&record.user_id)))
+    }
+
+    /// every user with needs, and the last time they added or confirmed one of them
+    pub fn last_confirmed_needs(&self) -> HashMap<String, Option<u64>> {
+        let mut users: HashMap<String, Option<u64>> = HashMap::new();
+
+        for cosmetic in self.all.iter() {
+            for record in self.need_records(cosmetic) {
+                let confirmed = users.entry(record.user_id).or_default();
+                *confirmed = (*confirmed).max(record.confirmed_at.or(record.created_at));
+            }
+        }
+
+        users
+    }
+
+    /// moves all of a user's needs into `database/archived/<user id>.txt` so they stop getting pinged, returning how many there were
+    pub fn archive_needs(&self, user_id: &str) -> usize {
+        self.move_needs(user_id, &create_archived_path(user_id))
+    }
+
+    /// puts a user's archived needs back with their old timestamps, returning the cosmetics
+    pub fn restore_needs(&self, user_id: &str) -> Vec<String> {
+        self.restore_needs_from(user_id, &create_archived_path(user_id))
+    }
+
+    /// takes all of a user's needs out of the cosmetic files and adds them to the end of another file
+    fn move_needs(&self, user_id: &str, path: &str) -> usize {
+        let mut moved = 0;
+
+        for cosmetic in self.all.iter() {
+            if let Some(record) = self.need_record(user_id, cosmetic) {
+                append_line(path, cosmetic, &format!("{} {}", record.to_line(), cosmetic));
+                self.remove(cosmetic, user_id);
+                moved += 1;
+            }
+        }
+
+        moved
+    }
+
+    /// adds back the needs moved with `move_needs`, then removes the file
+    fn restore_needs_from(&self, user_id: &str, path: &str) -> Vec<String> {
+        let Ok(moved) = std::fs::read_to_string(path) else {
+            return Vec::new();
+        };
+
+        let mut restored = Vec::new();
+
+        // the record's fields come first, since cosmetics can have spaces
+        for line in moved.lines() {
+            let fields = Vec::from_iter(line.splitn(5, ' '));
+
+            let [user, created, confirmed, priority, cosmetic] = fields[..] else {
+                continue;
+            };
+
+            let Some(record) = NeedRecord::parse(&format!("{} {} {} {}", user, created, confirmed, priority)) else {
+                continue;
+            };
+
+            if self.is_cosmetic(cosmetic) && !self.needs(user_id, cosmetic) {
+                self.add_record(cosmetic, &record);
+                restored.push(cosmetic.to_string());
+            }
+        }
+
+        let _ = std::fs::remove_file(path);
+
+        restored
+    }
+
+    pub fn need_record(&self, user_id: &str, cosmetic: &str) -> Option<NeedRecord> {
+        self.need_records(cosmetic).into_iter().find(|record| record.user_id == user_id)
+    }
+
+    pub fn add(&self, cosmetic: &str, user_id: &str, priority: Priority) {
+        self.add_record(cosmetic, &NeedRecord::new(user_id, priority));
+    }
+
+    /// adds an existing record back, keeping it's timestamps
+    pub fn add_record(&self, cosmetic: &str, record: &NeedRecord) {
+        append_line(&create_path(cosmetic), cosmetic, &record.to_line());
+    }
+
+    /// records that a user still needs a cosmetic
+    pub fn confirm(&self, cosmetic: &str, user_id: &str) {
+        self.update_need_record(cosmetic, user_id, |record| record.confirmed_at = Some(now()));
+    }
+
+    pub fn set_priority(&self, cosmetic: &str, user_id: &str, priority: Priority) {
+        self.update_need_record(cosmetic, user_id, |record| record.priority = priority);
+    }
+
+    fn update_need_record(&self, cosmetic: &str, user_id: &str, update: impl Fn(&mut NeedRecord)) {
+        let mut records = self.need_records(cosmetic);
+
+        for record in records.iter_mut().filter(|record| record.user_id == user_id) {
+            update(record);
+        }
+
+        self.write_need_records(cosmetic, &records);
+    }
+
+    fn write_need_records(&self, cosmetic: &str, records: &[NeedRecord]) {
+        let lines = String::from_iter(records.iter().map(|record| format!("{}\n", record.to_line())));
+
+        std::fs::write(create_path(cosmetic), lines).unwrap_or_else(|_| panic!("could not write to file for cosmetic {}", cosmetic));
+    }
+
+    /// rewrites need files that still have lines in an older format into the current one, with unknown timestamps.
+    /// files that are already up to date are left alone
+    fn migrate_need_records(&self) {
+        for cosmetic in self.all.iter() {
+            let lines = read_users(&create_path(cosmetic));
+
+            let legacy = lines.iter().any(|line| NeedRecord::parse(line).map(|record| record.to_line()).as_ref() != Some(line));
+
+            if legacy {
+                self.write_need_records(cosmetic, &self.need_records(cosmetic));
+            }
+        }
+    }
+
+    /// everything a user needs, sorted by priority then name within each category
+    pub fn needed_by_category(&self, user_id: &str) -> Vec<(CosmeticCategory, Vec<(String, NeedRecord)>)> {
+        Vec::from_iter(CosmeticCategory::ALL.iter().map(|&category| {
+            let mut needs = Vec::from_iter(
+                self.cosmetics_in_category(category)
+                .filter_map(|cosmetic| Some((cosmetic.clone(), self.need_record(user_id, cosmetic)?)))
+            );
+
+            // stable so each priority stays in name order
+            needs.sort_by_key(|(_, record)| record.priority);
+
+            (category, needs)
+        }))
+    }
+
+    /// currently naive approach
+    pub fn needed_by(&self, user_id: &str) -> Vec<String> {
+        let mut needed = Vec::new();
+
+        for cosmetic in self.all.iter() {
+            if self.needs(user_id, cosmetic) {
+                needed.push(cosmetic.clone());
+            }
+        }
+
+        needed
+    }
+
+    pub fn remove(&self, cosmetic: &str, user_id: &str) {
+        remove_user(&create_path(cosmetic), user_id);
+    }
+
+    pub fn has_spare(&self, user_id: &str, cosmetic: &str) -> bool {
+        self.who_has_spare(cosmetic).iter().any(|line| line == user_id)
+    }
+
+    pub fn who_has_spare(&self, cosmetic: &str) -> Vec<String> {
+        read_users(&create_spare_path(cosmetic))
+    }
+
+    pub fn add_spare(&self, cosmetic: &str, user_id: &str) {
+        append_line(&create_spare_path(cosmetic), cosmetic, user_id);
+    }
+
+    /// same naive approach as `needed_by`
+    pub fn spares_of(&self, user_id: &str) -> Vec<String> {
+        let mut spares = Vec::new();
+
+        for cosmetic in self.all.iter() {
+            if self.has_spare(user_id, cosmetic) {
+                spares.push(cosmetic.clone());
             }
         }
-    };
 
+        spares
+    }
 
-    cosmetic_reply.delete(ctx).await.unwrap();
+    pub fn remove_spare(&self, cosmetic: &str, user_id: &str) {
+        remove_user(&create_spare_path(cosmetic), user_id);
+    }
 
+    /// removes all of a user's spares, returning how many there were
+    pub fn forget_spares(&self, user_id: &str) -> usize {
+        let spares = self.spares_of(user_id);
 
-    Some(cosmetic.clone())
-}
+        for cosmetic in spares.iter() {
+            self.remove_spare(cosmetic, user_id);
+        }
 
+        spares.len()
+    }
 
+    pub fn owns(&self, user_id: &str, cosmetic: &str) -> bool {
+        read_users(&create_owned_path(cosmetic)).iter().any(|line| line == user_id)
+    }
 
-#[derive(Clone, Copy)]
-pub enum CosmeticCategory {
-    Hat,
-    Top,
-    Bottom,
-    Accessory,
-    Vest,
-    Belt,
-}
+    /// records that a user has unlocked a cosmetic
+    pub fn add_owned(&self, cosmetic: &str, user_id: &str) {
+        if !self.owns(user_id, cosmetic) {
+            append_line(&create_owned_path(cosmetic), cosmetic, user_id);
+        }
+    }
 
-impl CosmeticCategory {
-    pub const ALL: &'static [CosmeticCategory] = &[
-        Self::Hat,
-        Self::Top,
-        Self::Bottom,
-        Self::Accessory,
-        Self::Vest,
-        Self::Belt,
-    ];
-}
+    pub fn remove_owned(&self, cosmetic: &str, user_id: &str) {
+        remove_user(&create_owned_path(cosmetic), user_id);
+    }
 
-impl std::fmt::Display for CosmeticCategory {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            Self::Hat => "Hat",
-            Self::Top => "Top",
-            Self::Bottom => "Bottom",
-            Self::Accessory => "Accessory",
-            Self::Vest => "Vest",
-            Self::Belt => "Belt",
-        })
+    /// whether the user needs or has a spare of anything, stopping at the first one found
+    pub fn is_known(&self, user_id: &str) -> bool {
+        self.all.iter().any(|cosmetic| self.needs(user_id, cosmetic) || self.has_spare(user_id, cosmetic))
     }
-}
 
-impl CosmeticCategory {
-    pub fn to_id(&self) -> &'static str {
-        match self {
-            Self::Hat => "0",
-            Self::Top => "1",
-            Self::Bottom => "2",
-            Self::Accessory => "3",
-            Self::Vest => "4",
-            Self::Belt => "5",
+    /// every user that needs something or has a spare
+    pub fn known_users(&self) -> HashSet<String> {
+        let mut users = HashSet::new();
+
+        for cosmetic in self.all.iter() {
+            users.extend(self.who_needs(cosmetic));
+            users.extend(self.who_has_spare(cosmetic));
         }
+
+        users
     }
 
-    pub fn from_id(id: &str) -> Option<Self> {
-        match id {
-            "0" => Some(Self::Hat),
-            "1" => Some(Self::Top),
-            "2" => Some(Self::Bottom),
-            "3" => Some(Self::Accessory),
-            "4" => Some(Self::Vest),
-            "5" => Some(Self::Belt),
-            _ => None,
-        }
+    /// every user that has changed one of their settings
+    pub fn users_with_settings(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir("database/users") else {
+            return Vec::new();
+        };
+
+        Vec::from_iter(
+            entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| Some(entry.path().file_stem()?.to_str()?.to_string()))
+        )
     }
-}
 
+    pub fn user_settings(&self, user_id: &str) -> UserSettings {
+        UserSettings::from_key_values(&read_key_values(&create_user_path(user_id)))
+    }
 
+    pub fn set_user_settings(&self, user_id: &str, settings: &UserSettings) {
+        write_key_values(&create_user_path(user_id), settings.to_key_values());
+        self.update_snoozed(user_id, settings);
+    }
 
-pub struct Database {
-    hats: Vec<String>,
-    tops: Vec<String>,
-    bottoms: Vec<String>,
-    accessories: Vec<String>,
-    vests: Vec<String>,
-    belts: Vec<String>,
+    /// the last time a user used a command, kept in `database/activity/<user id>.txt` apart from their settings
+    /// so using the bot doesn't give everyone a settings file
+    pub fn last_active(&self, user_id: &str) -> Option<u64> {
+        std::fs::read_to_string(create_activity_path(user_id)).ok()?.trim().parse().ok()
+    }
 
-    all: Vec<String>,
-}
+    pub fn set_last_active(&self, user_id: &str, time: u64) {
+        let path = create_activity_path(user_id);
+        std::fs::write(&path, time.to_string()).unwrap_or_else(|_| panic!("could not write to {:?}", path));
+    }
 
+    /// users that are snoozed or on vacation at a time, kept in one `<user id> <until>` line each in `database/snoozed.txt`
+    /// so leaving them out doesn't need everyone's settings read
+    pub fn snoozed_users(&self, time: u64) -> HashSet<String> {
+        HashSet::from_iter(read_users(SNOOZED_PATH).into_iter().filter_map(|line| {
+            let (user_id, until) = line.split_once(' ')?;
+            (until.parse::<u64>().ok()? > time).then(|| user_id.to_string())
+        }))
+    }
 
-impl Database {
-    pub fn new() -> Database {
+    /// keeps the user's line in `database/snoozed.txt` in step with their settings, dropping snoozes that are over
+    fn update_snoozed(&self, user_id: &str, settings: &UserSettings) {
+        let time = now();
 
-        let mut database = Database {
-            hats: Vec::new(),
-            tops: Vec::new(),
-            bottoms: Vec::new(),
-            accessories: Vec::new(),
-            vests: Vec::new(),
-            belts: Vec::new(),
+        let until = settings.snoozed_until.max(settings.vacation_until).filter(|&until| until > time);
+        let wanted = until.map(|until| format!("{} {}", user_id, until));
 
-            all: Vec::new(),
-        };
+        let lines = read_users(SNOOZED_PATH);
 
-        for category in CosmeticCategory::ALL {
+        // most settings changes have nothing to do with snoozing
+        if lines.iter().find(|line| line.split(' ').next() == Some(user_id)) == wanted.as_ref() {
+            return;
+        }
 
-            let path = format!("cosmetics/{}.txt", category);
+        let still_snoozed = |line: &String| line.split_once(' ').and_then(|(_, until)| until.parse::<u64>().ok()).is_some_and(|until| until > time);
 
-            let cosmetics = std::fs::read_to_string(&path).expect(&format!("could not read {:?}", path));
+        let lines = String::from_iter(
+            lines.iter()
+            .filter(|line| line.split(' ').next() != Some(user_id) && still_snoozed(line))
+            .chain(wanted.as_ref())
+            .map(|line| format!("{}\n", line))
+        );
 
-            let cosmetics = filter_allowed_characters(cosmetics);
+        std::fs::write(SNOOZED_PATH, lines).expect("could not write to the snoozed users");
+    }
 
-            let mut cosmetics = Vec::from_iter(
-                cosmetics.split("\n")
-                .map(str::to_string)
-                .filter(|line| line.len() > 0)
-            );
+    /// saves a share for a user to be told about when their quiet hours end
+    pub fn queue_share(&self, user_id: &str, link: &str, description: &str) {
+        append_line(&create_queued_path(user_id), description, &format!("{} {}", link, description));
+    }
 
-            for cosmetic in cosmetics.iter() {
-                database.all.push(cosmetic.clone());
-            }
+    pub fn users_with_queued_shares(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir("database/queued") else {
+            return Vec::new();
+        };
 
-            cosmetics.sort();
+        Vec::from_iter(
+            entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "txt"))
+            .filter_map(|entry| Some(entry.path().file_stem()?.to_str()?.to_string()))
+        )
+    }
 
-            *match category {
-                CosmeticCategory::Hat => &mut database.hats,
-                CosmeticCategory::Top => &mut database.tops,
-                CosmeticCategory::Bottom => &mut database.bottoms,
-                CosmeticCategory::Accessory => &mut database.accessories,
-                CosmeticCategory::Vest => &mut database.vests,
-                CosmeticCategory::Belt => &mut database.belts,
-            } = cosmetics;
+    /// the links and descriptions of a user's queued shares, which are removed from the queue.
+    ///
+    /// the file is moved out of the way before it's read, so shares queued in the meantime go in a new one
+    pub fn take_queued_shares(&self, user_id: &str) -> Vec<(String, String)> {
+        let path = create_queued_path(user_id);
+        let taken = format!("{}.taken", path);
+
+        if std::fs::rename(&path, &taken).is_err() {
+            return Vec::new();
         }
 
-        database
+        let shares = std::fs::read_to_string(&taken).unwrap_or_default();
+
+        let _ = std::fs::remove_file(&taken);
+
+        Vec::from_iter(
+            shares.split('\n')
+            .filter_map(|line| line.split_once(' '))
+            .map(|(link, description)| (link.to_string(), description.to_string()))
+        )
     }
 
-    pub fn cosmetics_in_category(&self, category: CosmeticCategory) -> impl Iterator<Item = &String> + '_ {
-        match category {
-            CosmeticCategory::Hat => self.hats.iter(),
-            CosmeticCategory::Top => self.tops.iter(),
-            CosmeticCategory::Bottom => self.bottoms.iter(),
-            CosmeticCategory::Accessory => self.accessories.iter(),
-            CosmeticCategory::Vest => self.vests.iter(),
-            CosmeticCategory::Belt => self.belts.iter(),
+    pub fn save_share(&self, share: &ShareRecord) {
+        let path = match share.copy {
+            0 => format!("database/shares/{}.txt", share.id),
+            copy => format!("database/shares/{}-{}.txt", share.id, copy),
+        };
+
+        write_key_values(&path, share.to_key_values());
+    }
+
+    /// shares still open when the bot stopped can't be claimed anymore, so they're counted as timed out
+    fn close_interrupted_shares(&self) {
+        for mut share in self.shares() {
+            if share.outcome == ShareOutcome::Open {
+                share.close(ShareOutcome::TimedOut, None);
+                self.save_share(&share);
+            }
         }
     }
 
-    pub fn needs(&self, user_id: &str, cosmetic: &str) -> bool {
-        self.who_needs(cosmetic).iter().any(|line| line == user_id)
+    /// every share that has been recorded, in no particular order
+    pub fn shares(&self) -> Vec<ShareRecord> {
+        let Ok(entries) = std::fs::read_dir("database/shares") else {
+            return Vec::new();
+        };
+
+        Vec::from_iter(
+            entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| ShareRecord::from_key_values(&read_key_values(entry.path().to_str()?)))
+        )
     }
 
-    pub fn who_needs(&self, cosmetic: &str) -> Vec<String> {
-        if let Ok(users) = std::fs::read_to_string(create_path(cosmetic)) {
+    /// the keys of trades that have been proposed, and when they were
+    pub fn proposed_trades(&self) -> HashMap<String, u64> {
+        let Ok(trades) = std::fs::read_to_string("database/proposed_trades.txt") else {
+            return HashMap::new();
+        };
+
+        // the timestamp goes first since trade keys can have spaces in them
+        HashMap::from_iter(
+            trades.split('\n')
+            .filter_map(|line| line.split_once(' '))
+            .filter_map(|(proposed_at, key)| Some((key.to_string(), proposed_at.parse().ok()?)))
+        )
+    }
 
-            let users = filter_allowed_characters(users);
+    pub fn set_proposed_trades(&self, trades: &HashMap<String, u64>) {
+        let trades = String::from_iter(trades.iter().map(|(key, proposed_at)| format!("{} {}\n", proposed_at, key)));
 
-            Vec::from_iter(
-                users.split("\n")
-                .map(str::to_string)
-                .filter(|line| line.len() > 0)
-            )
-        } else {
-            Vec::new()
+        std::fs::write("database/proposed_trades.txt", trades).expect("could not write to the proposed trades");
+    }
+
+    pub fn all_cosmetics(&self) -> impl Iterator<Item = &String> + '_ {
+        self.all.iter()
+    }
+
+    pub fn is_cosmetic(&self, cosmetic: &str) -> bool {
+        self.all.iter().any(|c| c == cosmetic)
+    }
+
+    /// every cosmetic containing `partial`, ignoring case
+    pub fn search(&self, partial: &str) -> impl Iterator<Item = &String> + '_ {
+        let partial = partial.to_lowercase();
+        self.all.iter().filter(move |cosmetic| cosmetic.to_lowercase().contains(&partial))
+    }
+
+    pub fn forget(&self, user_id: &str) {
+
+        for cosmetic in self.all.iter() {
+            self.remove(cosmetic, user_id);
+            self.remove_spare(cosmetic, user_id);
+            self.remove_owned(cosmetic, user_id);
         }
+
+        let _ = std::fs::remove_file(create_user_path(user_id));
+        let _ = std::fs::remove_file(create_queued_path(user_id));
+        let _ = std::fs::remove_file(create_archived_path(user_id));
+        let _ = std::fs::remove_file(create_activity_path(user_id));
+        let _ = std::fs::remove_dir_all(create_tombstone_path(user_id));
+        remove_user(SNOOZED_PATH, user_id);
+
+        self.anonymise_shares(user_id);
     }
 
-    pub fn add(&self, cosmetic: &str, user_id: &str) {
-        let mut file = std::fs::OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(create_path(cosmetic))
-            .expect(&format!("could not open file for cosmetic {}", cosmetic));
+    /// takes a user out of the share history, which is kept for the stats
+    fn anonymise_shares(&self, user_id: &str) {
+        for mut share in self.shares() {
+            let finder = share.finder == user_id;
+            let claimant = share.claimant.as_deref() == Some(user_id);
+
+            if !finder && !claimant {
+                continue;
+            }
+
+            if finder {
+                share.finder = FORGOTTEN_USER.to_string();
+            }
+
+            if claimant {
+                share.claimant = None;
+            }
 
-        use std::io::Write;
-        writeln!(file, "{}", user_id).expect(&format!("could not write to file for cosmetic {}", cosmetic));
+            self.save_share(&share);
+        }
     }
 
-    /// currently naive approach
-    pub fn needed_by(&self, user_id: &str) -> Vec<String> {
-        let mut needed = Vec::new();
+    /// moves everything about a user into `database/tombstones/<user id>/`, where it's kept until `purge_at` in case they want it back
+    pub fn tombstone(&self, user_id: &str, purge_at: u64) {
+        let tombstone = create_tombstone_path(user_id);
+
+        std::fs::create_dir_all(&tombstone).expect("could not create the tombstone directory");
+
+        // written first, so a tombstone left half made by a crash still gets purged
+        write_key_values(&format!("{}/tombstone.txt", tombstone), vec![("purge_at", purge_at.to_string())]);
+
+        self.move_needs(user_id, &format!("{}/needs.txt", tombstone));
 
         for cosmetic in self.all.iter() {
-            if self.needs(user_id, cosmetic) {
-                needed.push(cosmetic.clone());
+            if self.has_spare(user_id, cosmetic) {
+                append_line(&format!("{}/spares.txt", tombstone), cosmetic, cosmetic);
+                self.remove_spare(cosmetic, user_id);
+            }
+
+            if self.owns(user_id, cosmetic) {
+                append_line(&format!("{}/owned.txt", tombstone), cosmetic, cosmetic);
+                self.remove_owned(cosmetic, user_id);
             }
         }
 
-        needed
+        let _ = std::fs::rename(create_user_path(user_id), format!("{}/settings.txt", tombstone));
+        let _ = std::fs::rename(create_queued_path(user_id), format!("{}/queued.txt", tombstone));
+        let _ = std::fs::rename(create_archived_path(user_id), format!("{}/archived.txt", tombstone));
+        let _ = std::fs::rename(create_activity_path(user_id), format!("{}/activity.txt", tombstone));
+        remove_user(SNOOZED_PATH, user_id);
     }
 
-    pub fn remove(&self, cosmetic: &str, user_id: &str) {
+    /// puts back everything from a user's tombstone, returning false if they don't have one
+    pub fn restore_tombstone(&self, user_id: &str) -> bool {
+        let tombstone = create_tombstone_path(user_id);
+
+        if !std::path::Path::new(&tombstone).exists() {
+            return false;
+        }
+
+        self.restore_needs_from(user_id, &format!("{}/needs.txt", tombstone));
+
+        for cosmetic in read_users(&format!("{}/spares.txt", tombstone)).iter().filter(|cosmetic| self.is_cosmetic(cosmetic)) {
+            if !self.has_spare(user_id, cosmetic) {
+                self.add_spare(cosmetic, user_id);
+            }
+        }
+
+        for cosmetic in read_users(&format!("{}/owned.txt", tombstone)).iter().filter(|cosmetic| self.is_cosmetic(cosmetic)) {
+            self.add_owned(cosmetic, user_id);
+        }
+
+        // the settings made since they were forgotten are replaced by their old ones
+        let _ = std::fs::rename(format!("{}/settings.txt", tombstone), create_user_path(user_id));
+        let _ = std::fs::rename(format!("{}/queued.txt", tombstone), create_queued_path(user_id));
+        let _ = std::fs::rename(format!("{}/archived.txt", tombstone), create_archived_path(user_id));
+        let _ = std::fs::rename(format!("{}/activity.txt", tombstone), create_activity_path(user_id));
+        self.update_snoozed(user_id, &self.user_settings(user_id));
+
+        let _ = std::fs::remove_dir_all(&tombstone);
+
+        true
+    }
 
-        let path = create_path(cosmetic);
+    /// when a user's tombstone is purged, or `None` if they don't have one
+    pub fn tombstone_purge_at(&self, user_id: &str) -> Option<u64> {
+        read_key_values(&format!("{}/tombstone.txt", create_tombstone_path(user_id))).get("purge_at")?.parse().ok()
+    }
 
-        let Ok(users) = std::fs::read_to_string(&path) else {
+    /// deletes the tombstones whose grace period is over for good, along with any that don't say when to be purged
+    pub fn purge_tombstones(&self) {
+        let Ok(entries) = std::fs::read_dir("database/tombstones") else {
             return;
         };
 
-        let users = filter_allowed_characters(users);
+        let time = now();
 
-        let users = String::from_iter(
-            users.split('\n')
-            .filter(|&line| line != user_id)
-        );
+        for entry in entries.filter_map(Result::ok) {
+            let Some(user_id) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
 
-        std::fs::write(&path, users).unwrap();
+            if self.tombstone_purge_at(&user_id).is_none_or(|purge_at| purge_at <= time) {
+                let _ = std::fs::remove_dir_all(entry.path());
+                self.anonymise_shares(&user_id);
+            }
+        }
     }
+}
 
-    pub fn forget(&self, user_id: &str) {
 
-        for cosmetic in self.all.iter() {
-            self.remove(cosmetic, user_id);
-        }
+const SNOOZED_PATH: &str = "database/snoozed.txt";
+
+/// stands in for users that have been forgotten in the share history, no discord user has the id 0
+pub const FORGOTTEN_USER: &str = "0";
+
+
+fn read_users(path: &str) -> Vec<String> {
+    if let Ok(users) = std::fs::read_to_string(path) {
+
+        let users = filter_allowed_characters(users);
+
+        Vec::from_iter(
+            users.split('\n')
+            .map(str::to_string)
+            .filter(|line| !line.is_empty())
+        )
+    } else {
+        Vec::new()
     }
 }
 
+fn append_line(path: &str, cosmetic: &str, line: &str) {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .unwrap_or_else(|_| panic!("could not open file for cosmetic {}", cosmetic));
+
+    use std::io::Write;
+    writeln!(file, "{}", line).unwrap_or_else(|_| panic!("could not write to file for cosmetic {}", cosmetic));
+}
+
+/// removes the lines for a user, which start with their id
+fn remove_user(path: &str, user_id: &str) {
+
+    let Ok(users) = std::fs::read_to_string(path) else {
+        return;
+    };
+
+    let users = filter_allowed_characters(users);
+
+    // keep one user per line, joining without the newlines merges the remaining ids together
+    let users = String::from_iter(
+        users.split('\n')
+        .filter(|&line| !line.is_empty() && line.split(' ').next() != Some(user_id))
+        .map(|line| format!("{}\n", line))
+    );
+
+    std::fs::write(path, users).unwrap();
+}
+
+
+/// the current unix timestamp in seconds
+pub fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+}
+
+
+/// describes a length of time in seconds in the largest unit that fits
+pub fn format_duration(seconds: u64) -> String {
+    let (amount, unit) = match seconds {
+        0..=59 => (seconds, "second"),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+
+    format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+
+fn read_key_values(path: &str) -> HashMap<String, String> {
+    let Ok(values) = std::fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+
+    HashMap::from_iter(
+        values.split('\n')
+        .filter_map(|line| line.split_once(' '))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+    )
+}
+
+fn write_key_values(path: &str, values: Vec<(&str, String)>) {
+    let values = String::from_iter(
+        values.into_iter()
+        .map(|(key, value)| format!("{} {}\n", key, value))
+    );
+
+    std::fs::write(path, values).unwrap_or_else(|_| panic!("could not write to {:?}", path));
+}
+
 
 fn filter_allowed_characters(string: String) -> String {
     String::from_iter(
@@ -779,16 +2160,78 @@ fn filter_allowed_characters(string: String) -> String {
     ))
 }
 
-// filter's characters to create a valid path
-fn create_path(cosmetic: &str) -> String {
-    let valid_name = String::from_iter(cosmetic.chars().filter(|&c| {
+// filter's characters to create a valid file name
+fn valid_file_name(cosmetic: &str) -> String {
+    String::from_iter(cosmetic.chars().filter(|&c| {
         c.is_ascii_alphanumeric() || c == ' '
     }).map(|c| {
         match c {
             ' ' => '_',
             _ => c,
         }
-    }));
+    }))
+}
+
+fn create_path(cosmetic: &str) -> String {
+    format!("database/{}.txt", valid_file_name(cosmetic))
+}
+
+fn create_spare_path(cosmetic: &str) -> String {
+    format!("database/spares/{}.txt", valid_file_name(cosmetic))
+}
+
+fn create_owned_path(cosmetic: &str) -> String {
+    format!("database/owned/{}.txt", valid_file_name(cosmetic))
+}
+
+fn create_user_path(user_id: &str) -> String {
+    format!("database/users/{}.txt", valid_file_name(user_id))
+}
+
+fn create_tombstone_path(user_id: &str) -> String {
+    format!("database/tombstones/{}", valid_file_name(user_id))
+}
 
-    format!("database/{}.txt", valid_name)
+fn create_archived_path(user_id: &str) -> String {
+    format!("database/archived/{}.txt", valid_file_name(user_id))
+}
+
+fn create_queued_path(user_id: &str) -> String {
+    format!("database/queued/{}.txt", valid_file_name(user_id))
+}
+
+fn create_activity_path(user_id: &str) -> String {
+    format!("database/activity/{}.txt", valid_file_name(user_id))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_messages_arent_split() {
+        assert_eq!(split_message("one\ntwo\n\nthree"), vec!["one\ntwo\n\nthree".to_string()]);
+    }
+
+    #[test]
+    fn long_messages_split_between_lines() {
+        let line = "x".repeat(600);
+        let content = Vec::from_iter(std::iter::repeat_n(line.as_str(), 5)).join("\n");
+
+        let messages = split_message(&content);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0], Vec::from_iter(std::iter::repeat_n(line.as_str(), 3)).join("\n"));
+        assert_eq!(messages[1], Vec::from_iter(std::iter::repeat_n(line.as_str(), 2)).join("\n"));
+    }
+
+    #[test]
+    fn long_lines_are_broken_up() {
+        let messages = split_message(&"x".repeat(4500));
+
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|message| message.chars().count() <= MAX_MESSAGE_LENGTH));
+        assert_eq!(messages.concat().len(), 4500);
+    }
 }
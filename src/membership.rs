@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use serenity::all::{FullEvent, UserId};
+use serenity::prelude::*;
+
+use crate::{Data, Database, Error, UNKNOWN_MEMBER};
+
+
+/// cleans up after users that leave, or servers that remove the bot
+pub async fn handle_event(ctx: &Context, event: &FullEvent, data: &Data) -> Result<(), Error> {
+
+    match event {
+        FullEvent::GuildMemberRemoval { user, .. } => {
+            check_user(ctx, &data.database, user.id).await;
+        },
+        // unavailable servers are an outage, not the bot being removed
+        FullEvent::GuildDelete { incomplete, .. } if !incomplete.unavailable => {
+            data.guild_settings.remove(incomplete.id);
+
+            // there's no telling who was only in that server
+            tokio::spawn(sweep(ctx.clone(), data.database.clone()));
+        },
+        _ => {},
+    }
+
+    Ok(())
+}
+
+
+/// checks every user in the database still shares a server with the bot
+pub async fn sweep(ctx: Context, database: Arc<Database>) {
+
+    // the cache is empty until the servers have loaded, which would look like everyone has left
+    if ctx.cache.guilds().is_empty() {
+        return;
+    }
+
+    for user_id in database.known_users() {
+        let Ok(id) = user_id.parse::<u64>() else {
+            continue;
+        };
+
+        clean_up(&ctx, &database, UserId::new(id)).await;
+    }
+}
+
+/// cleans up after a user that left a server, if they have anything in the database to clean up
+async fn check_user(ctx: &Context, database: &Database, user_id: UserId) {
+
+    if !database.is_known(&user_id.to_string()) {
+        return;
+    }
+
+    clean_up(ctx, database, user_id).await;
+}
+
+/// archives the needs and forgets the spares of a user that isn't in any of the bot's servers anymore
+async fn clean_up(ctx: &Context, database: &Database, user_id: UserId) {
+
+    if shares_a_guild(ctx, user_id).await != Some(false) {
+        return;
+    }
+
+    let user = user_id.to_string();
+
+    let archived = database.archive_needs(&user);
+    let spares = database.forget_spares(&user);
+
+    if archived > 0 || spares > 0 {
+        println!("user {} left every server, archived {} needs and forgot {} spares", user_id, archived, spares);
+    }
+}
+
+/// whether the user is in any of the bot's servers, or `None` if discord couldn't say for sure
+async fn shares_a_guild(ctx: &Context, user_id: UserId) -> Option<bool> {
+
+    // anyone in the member cache doesn't need asking discord about
+    let cached = ctx.cache.guilds().into_iter().any(|guild_id| {
+        ctx.cache.guild(guild_id).is_some_and(|guild| guild.members.contains_key(&user_id))
+    });
+
+    if cached {
+        return Some(true);
+    }
+
+    for guild_id in ctx.cache.guilds() {
+        match guild_id.member(ctx, user_id).await {
+            Ok(_) => return Some(true),
+            Err(serenity::Error::Http(serenity::all::HttpError::UnsuccessfulRequest(response))) if response.error.code == UNKNOWN_MEMBER => continue,
+            Err(_) => return None,
+        }
+    }
+
+    Some(false)
+}
@@ -0,0 +1,157 @@
+
+
+/// how much a user wants a cosmetic, high priority users get pinged first when it's found
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Priority {
+    #[name = "High"]
+    High,
+    #[default]
+    #[name = "Normal"]
+    Normal,
+    #[name = "Low"]
+    Low,
+}
+
+impl Priority {
+    pub fn to_id(self) -> &'static str {
+        match self {
+            Self::High => "high",
+            Self::Normal => "normal",
+            Self::Low => "low",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "high" => Some(Self::High),
+            "normal" => Some(Self::Normal),
+            "low" => Some(Self::Low),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::High => "high priority",
+            Self::Normal => "normal priority",
+            Self::Low => "low priority",
+        })
+    }
+}
+
+
+/// one user needing a cosmetic, stored as a `<user id> <created at> <last confirmed at> <priority>` line in the cosmetic's file.
+///
+/// the timestamps are unix seconds, or `unknown` for needs recorded before timestamps were kept
+#[derive(Clone)]
+pub struct NeedRecord {
+    pub user_id: String,
+    pub created_at: Option<u64>,
+    /// the last time the user said they still need it
+    pub confirmed_at: Option<u64>,
+    pub priority: Priority,
+}
+
+impl NeedRecord {
+    pub fn new(user_id: &str, priority: Priority) -> Self {
+        let now = crate::now();
+
+        NeedRecord {
+            user_id: user_id.to_string(),
+            created_at: Some(now),
+            confirmed_at: Some(now),
+            priority,
+        }
+    }
+
+    /// also reads the older formats that are missing fields from the end
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split(' ');
+
+        let user_id = fields.next().filter(|id| !id.is_empty())?.to_string();
+        let created_at = fields.next().and_then(|time| time.parse().ok());
+        let confirmed_at = fields.next().and_then(|time| time.parse().ok());
+        let priority = fields.next().and_then(Priority::from_id).unwrap_or_default();
+
+        Some(NeedRecord {
+            user_id,
+            created_at,
+            confirmed_at,
+            priority,
+        })
+    }
+
+    pub fn to_line(&self) -> String {
+        let format_time = |time: Option<u64>| time.map_or("unknown".to_string(), |time| time.to_string());
+
+        format!("{} {} {} {}", self.user_id, format_time(self.created_at), format_time(self.confirmed_at), self.priority.to_id())
+    }
+
+    /// how long the user has needed it for, in seconds
+    pub fn age(&self) -> Option<u64> {
+        Some(crate::now().saturating_sub(self.created_at?))
+    }
+
+    /// describes how long the user has needed it for
+    pub fn describe_age(&self) -> String {
+        match self.age() {
+            Some(age) => format!("needed for {}", crate::format_duration(age)),
+            None => "needed since before ages were recorded".to_string(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_current_lines() {
+        let record = NeedRecord::parse("1234 100 200 high").unwrap();
+
+        assert_eq!(record.user_id, "1234");
+        assert_eq!(record.created_at, Some(100));
+        assert_eq!(record.confirmed_at, Some(200));
+        assert!(record.priority == Priority::High);
+        assert_eq!(record.to_line(), "1234 100 200 high");
+    }
+
+    #[test]
+    fn parses_unknown_timestamps() {
+        let record = NeedRecord::parse("1234 unknown unknown low").unwrap();
+
+        assert_eq!(record.created_at, None);
+        assert_eq!(record.confirmed_at, None);
+        assert!(record.priority == Priority::Low);
+        assert_eq!(record.to_line(), "1234 unknown unknown low");
+    }
+
+    #[test]
+    fn parses_legacy_id_only_lines() {
+        let record = NeedRecord::parse("1234").unwrap();
+
+        assert_eq!(record.user_id, "1234");
+        assert_eq!(record.created_at, None);
+        assert_eq!(record.confirmed_at, None);
+        assert!(record.priority == Priority::Normal);
+        assert_eq!(record.to_line(), "1234 unknown unknown normal");
+    }
+
+    #[test]
+    fn parses_legacy_lines_without_priority() {
+        let record = NeedRecord::parse("1234 100 200").unwrap();
+
+        assert_eq!(record.created_at, Some(100));
+        assert_eq!(record.confirmed_at, Some(200));
+        assert!(record.priority == Priority::Normal);
+        assert_eq!(record.to_line(), "1234 100 200 normal");
+    }
+
+    #[test]
+    fn rejects_empty_lines() {
+        assert!(NeedRecord::parse("").is_none());
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use poise::{ChoiceParameter, CreateReply};
+
+use crate::needs::Priority;
+use crate::{Context, CosmeticCategory, Error};
+
+
+/// how a user hears about a share of something they need
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Delivery {
+    /// pinged in the share's channel, or however the server wants people pinged
+    #[default]
+    #[name = "Ping"]
+    Ping,
+    #[name = "Direct Message"]
+    DirectMessage,
+    /// no live pings, only the `/digest` if they've turned it on
+    #[name = "Digest Only"]
+    Digest,
+}
+
+impl Delivery {
+    pub fn to_id(self) -> &'static str {
+        match self {
+            Self::Ping => "ping",
+            Self::DirectMessage => "dm",
+            Self::Digest => "digest",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "ping" => Some(Self::Ping),
+            "dm" => Some(Self::DirectMessage),
+            "digest" => Some(Self::Digest),
+            _ => None,
+        }
+    }
+}
+
+
+/// how each user wants to be told about shares, stored with the rest of their `UserSettings`
+pub struct NotificationPrefs {
+    /// how they hear about each category, anything missing is `Delivery::Ping`
+    pub delivery: HashMap<CosmeticCategory, Delivery>,
+    /// needs below this priority don't notify them
+    pub min_priority: Priority,
+    /// no notifications at all
+    pub muted: bool,
+}
+
+impl Default for NotificationPrefs {
+    fn default() -> Self {
+        NotificationPrefs {
+            delivery: HashMap::new(),
+            min_priority: Priority::Low,
+            muted: false,
+        }
+    }
+}
+
+impl NotificationPrefs {
+    pub fn from_key_values(values: &HashMap<String, String>) -> Self {
+        let mut prefs = NotificationPrefs::default();
+
+        for category in CosmeticCategory::ALL {
+            if let Some(delivery) = values.get(delivery_key(*category)).and_then(|id| Delivery::from_id(id)) {
+                prefs.delivery.insert(*category, delivery);
+            }
+        }
+
+        if let Some(min_priority) = values.get("min_priority").and_then(|id| Priority::from_id(id)) {
+            prefs.min_priority = min_priority;
+        }
+
+        if let Some(muted) = values.get("muted") {
+            prefs.muted = muted == "true";
+        }
+
+        prefs
+    }
+
+    pub fn to_key_values(&self) -> Vec<(&'static str, String)> {
+        let mut values = vec![
+            ("min_priority", self.min_priority.to_id().to_string()),
+            ("muted", self.muted.to_string()),
+        ];
+
+        for category in CosmeticCategory::ALL {
+            if let Some(delivery) = self.delivery.get(category) {
+                values.push((delivery_key(*category), delivery.to_id().to_string()));
+            }
+        }
+
+        values
+    }
+
+    pub fn delivery(&self, category: CosmeticCategory) -> Delivery {
+        self.delivery.get(&category).copied().unwrap_or_default()
+    }
+
+    /// how they should hear about a need being found, or `None` if they shouldn't
+    pub fn route(&self, category: CosmeticCategory, priority: Priority) -> Option<Delivery> {
+        if self.muted || priority > self.min_priority {
+            return None;
+        }
+
+        Some(self.delivery(category))
+    }
+
+    pub fn describe(&self) -> String {
+        let categories = CosmeticCategory::ALL.iter().fold(String::new(), |acc, category| {
+            format!("{}\n**{}:** {}", acc, category, self.delivery(*category).name())
+        });
+
+        let muted = if self.muted { "\n\n**Muted**, you won't be told about any shares" } else { "" };
+
+        format!("You're told about needs down to **{}**{}{}", self.min_priority, categories, muted)
+    }
+}
+
+/// the keys need to be `'static` to be written
+fn delivery_key(category: CosmeticCategory) -> &'static str {
+    match category {
+        CosmeticCategory::Hat => "delivery_hat",
+        CosmeticCategory::Top => "delivery_top",
+        CosmeticCategory::Bottom => "delivery_bottom",
+        CosmeticCategory::Accessory => "delivery_accessory",
+        CosmeticCategory::Vest => "delivery_vest",
+        CosmeticCategory::Belt => "delivery_belt",
+    }
+}
+
+
+#[poise::command(slash_command)]
+pub async fn notifications(
+    ctx: Context<'_>,
+    #[description = "The category to change how you're told about, defaults to every category"]
+    category: Option<CosmeticCategory>,
+    #[description = "How you're told when something you need is found"]
+    delivery: Option<Delivery>,
+    #[description = "Only be told about needs with at least this priority"]
+    min_priority: Option<Priority>,
+    #[description = "Stop being told about shares at all"]
+    mute: Option<bool>,
+    #[description = "Days without using the bot before you're asked if you still need things, 0 never asks"]
+    #[min = 0]
+    #[max = 365]
+    check_in: Option<u64>,
+) -> Result<(), Error> {
+
+
+    let user_id = ctx.author().id.to_string();
+
+    let mut settings = ctx.data().database.user_settings(&user_id);
+    let prefs = &mut settings.notifications;
+
+    if let Some(delivery) = delivery {
+        let categories = match category {
+            Some(category) => vec![category],
+            None => CosmeticCategory::ALL.to_vec(),
+        };
+
+        for category in categories {
+            prefs.delivery.insert(category, delivery);
+        }
+    }
+
+    if let Some(min_priority) = min_priority {
+        prefs.min_priority = min_priority;
+    }
+
+    if let Some(mute) = mute {
+        prefs.muted = mute;
+    }
+
+    if let Some(check_in) = check_in {
+        settings.check_in = Some(check_in);
+    }
+
+    let check_in = match settings.check_in_days() {
+        Some(days) => format!("You'll be asked if you still need things after **{} days** without using the bot", days),
+        None => "You'll never be asked if you still need things".to_string(),
+    };
+
+    let content = format!("{}
+
+{}", settings.notifications.describe(), check_in);
+
+    ctx.data().database.set_user_settings(&user_id, &settings);
+
+    ctx.send(
+        CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+    ).await.unwrap();
+
+
+    Ok(())
+}
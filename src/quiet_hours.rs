@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+use poise::CreateReply;
+use serenity::all::UserId;
+use serenity::builder::CreateMessage;
+use serenity::prelude::*;
+
+use crate::{dm_user, split_message, Database, Error};
+
+
+const MINUTES_IN_DAY: i64 = 60 * 24;
+
+
+/// a daily window in the user's own time when they don't want to be pinged
+#[derive(Clone, Copy)]
+pub struct QuietHours {
+    /// minutes after midnight
+    pub start: u32,
+    pub end: u32,
+}
+
+impl QuietHours {
+    pub fn from_key_values(values: &HashMap<String, String>) -> Option<Self> {
+        Some(QuietHours {
+            start: values.get("quiet_start")?.parse().ok()?,
+            end: values.get("quiet_end")?.parse().ok()?,
+        })
+    }
+
+    pub fn to_key_values(self) -> Vec<(&'static str, String)> {
+        vec![
+            ("quiet_start", self.start.to_string()),
+            ("quiet_end", self.end.to_string()),
+        ]
+    }
+
+    /// whether it's quiet hours at a unix time, for a user `utc_offset` minutes ahead of utc
+    pub fn contains(&self, time: u64, utc_offset: i32) -> bool {
+        let local = ((time / 60) as i64 + utc_offset as i64).rem_euclid(MINUTES_IN_DAY) as u32;
+
+        // windows can go past midnight
+        if self.start <= self.end {
+            self.start <= local && local < self.end
+        } else {
+            local >= self.start || local < self.end
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} to {}", format_time(self.start), format_time(self.end))
+    }
+}
+
+
+/// where a user is, either a named timezone that follows daylight saving or a fixed offset from utc
+#[derive(Clone, Copy)]
+pub enum Timezone {
+    /// like `Europe/London`
+    Named(Tz),
+    /// minutes ahead of utc
+    Fixed(i32),
+}
+
+impl Timezone {
+    /// reads an offset like `UTC+2`, or a timezone name like `America/New_York`
+    pub fn parse(timezone: &str) -> Option<Self> {
+        parse_utc_offset(timezone).map(Self::Fixed).or_else(|| timezone.trim().parse().ok().map(Self::Named))
+    }
+
+    pub fn from_key_values(values: &HashMap<String, String>) -> Option<Self> {
+        match values.get("timezone") {
+            Some(name) => name.parse().ok().map(Self::Named),
+            None => values.get("utc_offset")?.parse().ok().map(Self::Fixed),
+        }
+    }
+
+    pub fn to_key_values(self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::Named(timezone) => vec![("timezone", timezone.name().to_string())],
+            Self::Fixed(offset) => vec![("utc_offset", offset.to_string())],
+        }
+    }
+
+    /// how many minutes ahead of utc the user is at a unix time
+    pub fn utc_offset(self, time: u64) -> i32 {
+        match self {
+            Self::Named(timezone) => DateTime::from_timestamp(time as i64, 0)
+                .map_or(0, |time| timezone.offset_from_utc_datetime(&time.naive_utc()).fix().local_minus_utc() / 60),
+            Self::Fixed(offset) => offset,
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            Self::Named(timezone) => timezone.name().to_string(),
+            Self::Fixed(offset) => format_utc_offset(offset),
+        }
+    }
+}
+
+
+/// reads a time of day like `22:00` or `7`, as minutes after midnight
+pub fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':').unwrap_or((time.trim(), "0"));
+
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+fn format_time(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// reads a timezone like `UTC+2`, `GMT-3:30` or `+5:30`, as minutes ahead of utc.
+///
+/// fixed offsets don't follow daylight saving, so users have to update it themselves or use a timezone name
+pub fn parse_utc_offset(timezone: &str) -> Option<i32> {
+    let timezone = timezone.trim().to_uppercase();
+    let offset = timezone.strip_prefix("UTC").or_else(|| timezone.strip_prefix("GMT")).unwrap_or(&timezone);
+
+    if offset.is_empty() {
+        return Some(0);
+    }
+
+    let (sign, offset) = if let Some(offset) = offset.strip_prefix('+') {
+        (1, offset)
+    } else if let Some(offset) = offset.strip_prefix('-') {
+        (-1, offset)
+    } else {
+        (1, offset)
+    };
+
+    let minutes = parse_time(offset)? as i32;
+
+    // real offsets go from -12:00 to +14:00
+    let limit = if sign < 0 { 12 * 60 } else { 14 * 60 };
+
+    (minutes <= limit).then_some(sign * minutes)
+}
+
+pub fn format_utc_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    format!("UTC{}{}", sign, format_time(offset.unsigned_abs()))
+}
+
+
+#[poise::command(slash_command)]
+pub async fn quiethours(
+    ctx: crate::Context<'_>,
+    #[description = "When quiet hours start in your time, like 22:00"]
+    start: Option<String>,
+    #[description = "When quiet hours end in your time, like 07:30"]
+    end: Option<String>,
+    #[description = "Your timezone, like Europe/London or America/New_York, or an offset like UTC+2"]
+    timezone: Option<String>,
+    #[description = "Turn quiet hours off"]
+    off: Option<bool>,
+) -> Result<(), Error> {
+
+
+    let user_id = ctx.author().id.to_string();
+
+    let mut settings = ctx.data().database.user_settings(&user_id);
+
+    let reply = |content: String| CreateReply::default().content(content).ephemeral(true);
+
+    if let Some(timezone) = timezone {
+        let Some(timezone) = Timezone::parse(&timezone) else {
+            ctx.send(reply(format!("\"{}\" isn't a timezone, use a name like `Europe/London` or an offset from UTC like `UTC-5:30`", timezone))).await.unwrap();
+            return Ok(());
+        };
+
+        settings.timezone = Some(timezone);
+    }
+
+    match (start, end) {
+        (Some(start), Some(end)) => {
+            let (Some(start), Some(end)) = (parse_time(&start), parse_time(&end)) else {
+                ctx.send(reply("Quiet hours need to be times like `22:00` and `07:30`".to_string())).await.unwrap();
+                return Ok(());
+            };
+
+            settings.quiet_hours = Some(QuietHours { start, end });
+        },
+        (None, None) => {},
+        _ => {
+            ctx.send(reply("Quiet hours need both a `start` and an `end`".to_string())).await.unwrap();
+            return Ok(());
+        },
+    }
+
+    if off == Some(true) {
+        settings.quiet_hours = None;
+    }
+
+    let content = match (settings.quiet_hours, settings.timezone) {
+        (Some(quiet_hours), Some(timezone)) => format!("Your quiet hours are **{}** in **{}**, pings during them are saved for a dm when they end", quiet_hours.describe(), timezone.describe()),
+        (Some(quiet_hours), None) => format!("Your quiet hours are **{}**, but you need to set your `timezone` for them to work", quiet_hours.describe()),
+        (None, Some(timezone)) => format!("Your timezone is **{}** and you don't have quiet hours", timezone.describe()),
+        (None, None) => "You don't have quiet hours".to_string(),
+    };
+
+    ctx.data().database.set_user_settings(&user_id, &settings);
+
+    ctx.send(reply(content)).await.unwrap();
+
+
+    Ok(())
+}
+
+
+/// dms everyone whose quiet hours have ended the shares they missed during them
+pub async fn send_summaries(ctx: &Context, database: &Database) {
+
+    for user_id in database.users_with_queued_shares() {
+        // users that snoozed since get the summary once they're back
+        let settings = database.user_settings(&user_id);
+
+        if settings.in_quiet_hours(crate::now()) || settings.snoozed(crate::now()) {
+            continue;
+        }
+
+        let shares = database.take_queued_shares(&user_id);
+
+        if shares.is_empty() {
+            continue;
+        }
+
+        let Ok(id) = user_id.parse::<u64>() else {
+            continue;
+        };
+
+        let list = shares.iter().fold(String::new(), |acc, (link, description)| format!("{}\n- {} {}", acc, description, link));
+
+        let content = format!("While you were in quiet hours these things you need were found{}", list);
+
+        for content in split_message(&content) {
+            if dm_user(ctx, UserId::new(id), CreateMessage::new().content(content)).await.is_err() {
+                println!("could not dm user {} their quiet hours summary", id);
+                break;
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiet_hours_within_a_day() {
+        let quiet_hours = QuietHours { start: 9 * 60, end: 17 * 60 };
+
+        assert!(quiet_hours.contains(9 * 3600, 0));
+        assert!(quiet_hours.contains(12 * 3600, 0));
+        assert!(!quiet_hours.contains(17 * 3600, 0));
+        assert!(!quiet_hours.contains(8 * 3600, 0));
+    }
+
+    #[test]
+    fn quiet_hours_past_midnight() {
+        let quiet_hours = QuietHours { start: 22 * 60, end: 7 * 60 };
+
+        assert!(quiet_hours.contains(22 * 3600, 0));
+        assert!(quiet_hours.contains(23 * 3600 + 59 * 60, 0));
+        assert!(quiet_hours.contains(0, 0));
+        assert!(quiet_hours.contains(6 * 3600 + 59 * 60, 0));
+        assert!(!quiet_hours.contains(7 * 3600, 0));
+        assert!(!quiet_hours.contains(12 * 3600, 0));
+        assert!(!quiet_hours.contains(21 * 3600 + 59 * 60, 0));
+    }
+
+    #[test]
+    fn quiet_hours_in_local_time() {
+        let quiet_hours = QuietHours { start: 22 * 60, end: 7 * 60 };
+
+        // 20:00 utc is 22:00 two hours ahead and 15:00 five hours behind
+        assert!(quiet_hours.contains(20 * 3600, 2 * 60));
+        assert!(!quiet_hours.contains(20 * 3600, -5 * 60));
+
+        // 03:00 utc is the previous evening five hours behind
+        assert!(quiet_hours.contains(3 * 3600, -5 * 60));
+        assert!(!quiet_hours.contains(3 * 3600, -6 * 60));
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("22:00"), Some(22 * 60));
+        assert_eq!(parse_time("07:30"), Some(7 * 60 + 30));
+        assert_eq!(parse_time("7"), Some(7 * 60));
+        assert_eq!(parse_time(" 0:05 "), Some(5));
+        assert_eq!(parse_time("24:00"), None);
+        assert_eq!(parse_time("12:60"), None);
+        assert_eq!(parse_time("noon"), None);
+        assert_eq!(parse_time("-1"), None);
+    }
+
+    #[test]
+    fn parses_utc_offsets() {
+        assert_eq!(parse_utc_offset("UTC"), Some(0));
+        assert_eq!(parse_utc_offset("utc+2"), Some(2 * 60));
+        assert_eq!(parse_utc_offset("GMT-3:30"), Some(-(3 * 60 + 30)));
+        assert_eq!(parse_utc_offset("+5:30"), Some(5 * 60 + 30));
+        assert_eq!(parse_utc_offset("UTC+14"), Some(14 * 60));
+        assert_eq!(parse_utc_offset("UTC-12"), Some(-12 * 60));
+        assert_eq!(parse_utc_offset("UTC+15"), None);
+        assert_eq!(parse_utc_offset("UTC-13"), None);
+        assert_eq!(parse_utc_offset("UTC-14"), None);
+        assert_eq!(parse_utc_offset("Europe/London"), None);
+    }
+
+    #[test]
+    fn parses_timezone_names() {
+        let Some(Timezone::Named(london)) = Timezone::parse("Europe/London") else {
+            panic!("Europe/London should be a timezone");
+        };
+
+        // 2024-01-15 and 2024-07-15 at noon utc, british summer time is an hour ahead
+        assert_eq!(Timezone::Named(london).utc_offset(1705320000), 0);
+        assert_eq!(Timezone::Named(london).utc_offset(1721044800), 60);
+
+        assert!(matches!(Timezone::parse("UTC-5"), Some(Timezone::Fixed(-300))));
+        assert!(Timezone::parse("Nowhere/Special").is_none());
+    }
+}
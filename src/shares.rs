@@ -0,0 +1,130 @@
+
+use std::collections::HashMap;
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ShareOutcome {
+    Open,
+    Claimed,
+    TimedOut,
+    Cancelled,
+}
+
+impl ShareOutcome {
+    pub fn to_id(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Claimed => "claimed",
+            Self::TimedOut => "timedout",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "open" => Some(Self::Open),
+            "claimed" => Some(Self::Claimed),
+            "timedout" => Some(Self::TimedOut),
+            "cancelled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+
+/// the history of one copy of a `/foundsomething` share, stored as `key value` lines in `database/shares/<id>.txt`,
+/// or `database/shares/<id>-<copy>.txt` for every copy after the first
+#[derive(Clone)]
+pub struct ShareRecord {
+    /// the id of the share's status message
+    pub id: u64,
+    /// which copy in the share this is, shares with several copies or cosmetics have a record for each
+    pub copy: usize,
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    pub finder: String,
+    pub cosmetic: String,
+    /// unix timestamps in seconds
+    pub created_at: u64,
+    pub closed_at: Option<u64>,
+    pub outcome: ShareOutcome,
+    pub claimant: Option<String>,
+}
+
+impl ShareRecord {
+    pub fn from_key_values(values: &HashMap<String, String>) -> Option<Self> {
+        Some(ShareRecord {
+            id: values.get("id")?.parse().ok()?,
+            copy: values.get("copy").and_then(|copy| copy.parse().ok()).unwrap_or(0),
+            guild_id: values.get("guild").and_then(|id| id.parse().ok()),
+            channel_id: values.get("channel")?.parse().ok()?,
+            finder: values.get("finder")?.clone(),
+            cosmetic: values.get("cosmetic")?.clone(),
+            created_at: values.get("created")?.parse().ok()?,
+            closed_at: values.get("closed").and_then(|time| time.parse().ok()),
+            outcome: ShareOutcome::from_id(values.get("outcome")?)?,
+            claimant: values.get("claimant").cloned(),
+        })
+    }
+
+    pub fn to_key_values(&self) -> Vec<(&'static str, String)> {
+        let mut values = vec![
+            ("id", self.id.to_string()),
+            ("copy", self.copy.to_string()),
+            ("channel", self.channel_id.to_string()),
+            ("finder", self.finder.clone()),
+            ("cosmetic", self.cosmetic.clone()),
+            ("created", self.created_at.to_string()),
+            ("outcome", self.outcome.to_id().to_string()),
+        ];
+
+        if let Some(guild_id) = self.guild_id {
+            values.push(("guild", guild_id.to_string()));
+        }
+
+        if let Some(closed_at) = self.closed_at {
+            values.push(("closed", closed_at.to_string()));
+        }
+
+        if let Some(claimant) = &self.claimant {
+            values.push(("claimant", claimant.clone()));
+        }
+
+        values
+    }
+
+    /// marks the share as finished
+    pub fn close(&mut self, outcome: ShareOutcome, claimant: Option<String>) {
+        self.outcome = outcome;
+        self.claimant = claimant;
+        self.closed_at = Some(crate::now());
+    }
+
+    /// a link to the share's status message
+    pub fn link(&self) -> String {
+        let guild = self.guild_id.map_or("@me".to_string(), |id| id.to_string());
+        format!("https://discord.com/channels/{}/{}/{}", guild, self.channel_id, self.id)
+    }
+
+    /// puts a claimed share back up for grabs after the handoff didn't work out
+    pub fn reopen(&mut self) {
+        self.outcome = ShareOutcome::Open;
+        self.claimant = None;
+        self.closed_at = None;
+    }
+}
+
+
+/// groups the records of copies of the same cosmetic from the same share, keeping the order they first appear in
+pub fn group_copies(records: impl IntoIterator<Item = ShareRecord>) -> Vec<Vec<ShareRecord>> {
+    let mut groups: Vec<Vec<ShareRecord>> = Vec::new();
+
+    for record in records {
+        match groups.iter_mut().find(|copies| copies[0].id == record.id && copies[0].cosmetic == record.cosmetic) {
+            Some(copies) => copies.push(record),
+            None => groups.push(vec![record]),
+        }
+    }
+
+    groups
+}
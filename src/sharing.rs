@@ -0,0 +1,1156 @@
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use poise::CreateReply;
+use serenity::all::{ButtonStyle, CacheHttp, ChannelId, ComponentInteraction, ComponentInteractionCollector, ComponentInteractionDataKind, Message, User, UserId};
+use serenity::builder::{CreateActionRow, CreateAllowedMentions, CreateButton, CreateInteractionResponse, CreateMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, CreateThread, EditMessage, EditThread};
+use serenity::futures::stream::{BoxStream, FuturesUnordered, StreamExt};
+use tokio::time::Instant;
+
+use crate::claiming::{self, ClaimMode, CLAIM_WINDOW, PRIORITY_WINDOW};
+use crate::needs::{NeedRecord, Priority};
+use crate::notifications::Delivery;
+use crate::shares::{ShareOutcome, ShareRecord};
+use crate::guild_settings::PingStyle;
+use crate::{acknowledge, cosmetic_select, dm_user, guild_settings, now, reply_privately, Context, Error};
+
+
+/// how long the finder and claimant have to confirm the cosmetic was handed over
+const HANDOFF_TIMEOUT: Duration = Duration::from_secs(60 * 30);
+
+/// how long the finder has to write their private handoff note
+const NOTE_TIMEOUT: Duration = Duration::from_secs(60 * 5);
+
+/// each cosmetic gets its own claim button, and they have to fit in 2 rows
+const MAX_ITEMS: usize = 10;
+
+const MAX_COPIES: usize = 10;
+
+
+/// cosmetics that have been found and are being given away.
+///
+/// messages are sent and edited through the channel rather than the interaction,
+/// because the interaction token expires long before a slow handoff finishes
+struct Share<'a> {
+    ctx: Context<'a>,
+    mode: ClaimMode,
+    /// how long people have to claim the open copies each time they're pinged
+    claim_timeout: Duration,
+    ping_style: PingStyle,
+    finder: UserId,
+    /// visible to everyone and gets updated with the status of the share
+    status: Message,
+    /// whether the server wants a thread for each share once it's claimed
+    use_thread: bool,
+    /// where the finder and claimants sort out the handoffs, opened on the first claim
+    thread: Option<ChannelId>,
+    /// how many handoffs are still going on, the thread is archived whenever the last one finishes
+    handoffs: Arc<AtomicUsize>,
+    /// the finder's private handoff note for whoever gets a copy, only ever kept in memory
+    note: Option<String>,
+    items: Vec<ShareItem>,
+}
+
+/// one of the cosmetics in a share, which can have several copies
+struct ShareItem {
+    cosmetic: String,
+    /// one record for each copy, which also keeps track of who claimed it
+    copies: Vec<ShareRecord>,
+    /// how the claimant of each copy was picked, for the status
+    picked: Vec<String>,
+    /// claimants that have confirmed they got their copy
+    received: Vec<UserId>,
+    /// claimants whose handoff didn't work out, who can't claim it again
+    excluded: Vec<UserId>,
+    /// everyone else that was interested, in the order they should get a copy if a handoff fails
+    next_in_line: Vec<User>,
+}
+
+/// a copy that someone has claimed, waiting to be handed over
+struct Claim {
+    item: usize,
+    copy: usize,
+    claimant: UserId,
+    /// how they were picked, to add to the end of the status
+    picked: String,
+}
+
+/// details like a lobby code that only the person that gets the share should see
+#[derive(poise::Modal)]
+#[name = "Private Handoff Note"]
+struct HandoffNote {
+    #[name = "Lobby code, time, platform or anything else"]
+    #[placeholder = "Only the people that get it will see this"]
+    #[paragraph]
+    #[max_length = 500]
+    note: String,
+}
+
+enum HandoffOutcome {
+    Received,
+    /// goes to the next person in line
+    Failed,
+    /// the claimant didn't show up or gave it up, so it goes back to everyone that needs it
+    Released,
+    TimedOut,
+}
+
+/// a copy being handed over, which owns everything it needs so it can run alongside the rest of the share
+struct Handoff {
+    ctx: serenity::all::Context,
+    finder: UserId,
+    /// where the handoff message goes, the share's thread if it has one
+    channel: ChannelId,
+    thread: Option<ChannelId>,
+    /// shared with the share and its other handoffs
+    handoffs: Arc<AtomicUsize>,
+    cosmetic: String,
+    note: Option<String>,
+    claim: Claim,
+}
+
+/// one round of pinging the users that need the open copies and waiting for them to claim them,
+/// which carries on while the copies that were already claimed are handed over
+struct Round {
+    /// where the claim buttons are, it gets sent again once everyone is pinged
+    message: Message,
+    /// the collector follows the claim message when it's sent again
+    message_id: Arc<AtomicU64>,
+    /// clicks on the claim message, and on the share's claim buttons in people's `/inbox`
+    interactions: BoxStream<'static, ComponentInteraction>,
+    /// the copies of each cosmetic that are up for grabs
+    open: Vec<Vec<usize>>,
+    /// whether each cosmetic was up for grabs when the round started, copies of them that are given back join the round
+    included: Vec<bool>,
+    /// the needs of everyone that could be pinged for each cosmetic
+    need_records: Vec<Vec<NeedRecord>>,
+    /// users that want a copy during the claim window, in the order they clicked
+    interested: Vec<Vec<User>>,
+    found: String,
+    content: String,
+    everyone_pinged: bool,
+    everyone_else_mentioned: Vec<String>,
+    everyone_else_dmed: Vec<String>,
+    everyone_else_queued: Vec<String>,
+    open_to_everyone: Instant,
+    window_ends: Instant,
+    timed_out: Instant,
+    /// set when the round ends before all of its copies were claimed
+    ended: Option<ShareOutcome>,
+    /// the share can be claimed from `/inbox` while the round is going
+    _live: LiveShare,
+}
+
+/// marks a share as taking claims until it's dropped, even if the share ends early
+struct LiveShare {
+    live_shares: Arc<std::sync::Mutex<HashSet<u64>>>,
+    id: u64,
+}
+
+/// what the share was waiting on
+enum Event {
+    Handoff(Claim, HandoffOutcome),
+    Click(Box<ComponentInteraction>),
+    Deadline,
+}
+
+
+#[poise::command(slash_command)]
+pub async fn foundsomething(
+    ctx: Context<'_>,
+    #[description = "How the person that gets it is picked, defaults to whoever clicks first"]
+    mode: Option<ClaimMode>,
+    #[description = "How many minutes people have to claim it, up to the server's limit"]
+    #[min = 1]
+    duration: Option<u64>,
+    #[description = "Write a note like a lobby code that only the people that get it will see"]
+    private_note: Option<bool>,
+) -> Result<(), Error> {
+
+    // the modal has to be the first response to the command
+    let note = match (private_note, ctx) {
+        (Some(true), poise::Context::Application(app_ctx)) => {
+            let Some(HandoffNote { note }) = poise::execute_modal(app_ctx, None::<HandoffNote>, Some(NOTE_TIMEOUT)).await? else {
+                return Ok(());
+            };
+
+            Some(note)
+        },
+        _ => None,
+    };
+
+    let settings = guild_settings(ctx);
+
+    let mode = mode.unwrap_or(settings.claim_mode);
+
+    let claim_timeout = settings.claim_timeout(duration.map(|minutes| Duration::from_secs(minutes * 60)));
+
+    let author_id = ctx.author().id;
+
+    // this is the main reply message, visible to everyone and gets updated with the status of the share
+    let status_reply = ctx.send(
+        CreateReply::default().content(format!("<@{}> has found a cosmetic", author_id))
+    ).await.unwrap();
+
+
+    // get the cosmetics
+    let Some(found) = items_select(ctx).await else {
+        status_reply.edit(ctx, CreateReply::default().content(format!("<@{}> found a cosmetic but cancelled", author_id))).await.unwrap();
+        return Ok(());
+    };
+
+    let reply_message = status_reply.message().await.unwrap().into_owned();
+
+    let description = Vec::from_iter(found.iter().map(|(cosmetic, _)| format!("**{}**", cosmetic))).join(", ");
+
+
+    // servers with a shares channel get the share posted there, with a link left where the command was used
+    let shares_channel = settings.announcement_channel.filter(|channel| *channel != ctx.channel_id());
+
+    let posted = match shares_channel {
+        Some(channel) => channel.send_message(ctx, CreateMessage::new()
+            .content(format!("<@{}> has found {}", author_id, description))
+            .allowed_mentions(CreateAllowedMentions::new())
+        ).await.ok(),
+        None => None,
+    };
+
+    let status = match posted {
+        Some(status) => {
+            status_reply.edit(ctx, CreateReply::default().content(format!("<@{}> is sharing {} in {}", author_id, description, status.link()))).await.unwrap();
+            status
+        },
+        None => reply_message,
+    };
+
+
+    // record every copy so they show up in stats
+    let mut copy_count = 0;
+
+    let items = Vec::from_iter(found.into_iter().map(|(cosmetic, copies)| {
+        let copies = Vec::from_iter((0..copies).map(|_| {
+            copy_count += 1;
+
+            let record = ShareRecord {
+                id: status.id.get(),
+                copy: copy_count - 1,
+                guild_id: ctx.guild_id().map(|id| id.get()),
+                channel_id: status.channel_id.get(),
+                finder: author_id.to_string(),
+                cosmetic: cosmetic.clone(),
+                created_at: now(),
+                closed_at: None,
+                outcome: ShareOutcome::Open,
+                claimant: None,
+            };
+            ctx.data().database.save_share(&record);
+
+            record
+        }));
+
+        ShareItem {
+            cosmetic,
+            picked: vec![String::new(); copies.len()],
+            copies,
+            received: Vec::new(),
+            excluded: Vec::new(),
+            next_in_line: Vec::new(),
+        }
+    }));
+
+    let mut share = Share {
+        ctx,
+        mode,
+        claim_timeout,
+        ping_style: settings.ping_style,
+        finder: author_id,
+        status,
+        use_thread: settings.share_threads && ctx.guild_id().is_some(),
+        thread: None,
+        handoffs: Arc::new(AtomicUsize::new(0)),
+        note,
+        items,
+    };
+
+    share.update_status().await;
+
+    // every claimed copy is handed over on its own, so one slow handoff doesn't hold up the rest of the share
+    let mut handoffs = FuturesUnordered::new();
+    let mut round: Option<Round> = None;
+
+    loop {
+        // copies that were given back go to whoever is next in line
+        for claim in share.pass_on() {
+            share.open_thread().await;
+            handoffs.push(share.handoff(claim));
+        }
+
+        // otherwise everyone that needs them gets pinged, joining the round that's going if their cosmetic is part of it
+        match round.as_mut() {
+            Some(current) => share.add_reopened(current).await,
+            None if share.has_open_copies() => round = Some(share.start_round().await),
+            None => {},
+        }
+
+        share.update_status().await;
+
+        let event = match round.as_mut() {
+            Some(current) => {
+                let deadline = current.deadline(share.mode);
+
+                tokio::select! {
+                    Some((claim, outcome)) = handoffs.next(), if !handoffs.is_empty() => Event::Handoff(claim, outcome),
+                    Some(interaction) = current.interactions.next() => Event::Click(Box::new(interaction)),
+                    _ = tokio::time::sleep_until(deadline) => Event::Deadline,
+                }
+            },
+            None => match handoffs.next().await {
+                Some((claim, outcome)) => Event::Handoff(claim, outcome),
+                None => break,
+            },
+        };
+
+        let claims = match (event, round.as_mut()) {
+            (Event::Handoff(claim, outcome), _) => {
+                share.finish_handoff(claim, outcome);
+                Vec::new()
+            },
+            (Event::Click(interaction), Some(current)) => share.click(current, *interaction).await,
+            (Event::Deadline, Some(current)) => share.deadline_passed(current).await,
+            (_, None) => Vec::new(),
+        };
+
+        // the thread is opened once there's a claim to sort out
+        for claim in claims {
+            share.open_thread().await;
+            handoffs.push(share.handoff(claim));
+        }
+
+        if round.as_ref().is_some_and(Round::is_over) {
+            share.end_round(round.take().unwrap()).await;
+        }
+    }
+
+    share.archive_thread().await;
+
+    Ok(())
+}
+
+
+/// the id of a claim button for a share shown in someone's `/inbox`
+pub fn inbox_claim_id(share_id: u64, cosmetic: &str) -> String {
+    format!("inbox:{}:{}", share_id, cosmetic)
+}
+
+/// the share and cosmetic an `/inbox` claim button is for
+pub fn parse_inbox_claim_id(id: &str) -> Option<(u64, &str)> {
+    let (share_id, cosmetic) = id.strip_prefix("inbox:")?.split_once(':')?;
+    Some((share_id.parse().ok()?, cosmetic))
+}
+
+
+/// asks the author for the cosmetics they found and how many of each
+async fn items_select(ctx: Context<'_>) -> Option<Vec<(String, usize)>> {
+
+    let mut items: Vec<(String, usize)> = Vec::new();
+
+    let add = |items: &mut Vec<(String, usize)>, cosmetic: String| {
+        match items.iter_mut().find(|(c, _)| *c == cosmetic) {
+            Some((_, copies)) => *copies = (*copies + 1).min(MAX_COPIES),
+            None => items.push((cosmetic, 1)),
+        }
+    };
+
+    add(&mut items, cosmetic_select(ctx).await?);
+
+    let create_prompt = |items: &[(String, usize)]| {
+        let list = items.iter().fold(String::new(), |acc, (cosmetic, copies)| format!("{}\n- **{}** x{}", acc, cosmetic, copies));
+
+        CreateReply::default()
+            .content(format!("You're sharing{}\n\nAdd another copy or cosmetic if you found more than one", list))
+            .ephemeral(true)
+            .components(vec![
+                CreateActionRow::Buttons(vec![
+                    CreateButton::new("share").style(ButtonStyle::Success).label("Share"),
+                    CreateButton::new("copy").label("Another Copy"),
+                    CreateButton::new("add").label("Add Cosmetic").disabled(items.len() >= MAX_ITEMS),
+                    CreateButton::new("cancel").style(ButtonStyle::Danger).label("Cancel"),
+                ]),
+            ])
+    };
+
+    loop {
+        let prompt_reply = ctx.send(create_prompt(&items)).await.unwrap();
+        let message = prompt_reply.message().await.unwrap();
+
+        let interaction = match message.await_component_interaction(&ctx.serenity_context().shard).timeout(guild_settings(ctx).select_timeout).await {
+            Some(interaction) => interaction,
+            None => {
+                prompt_reply.delete(ctx).await.unwrap();
+                ctx.send(CreateReply::default().ephemeral(true).content("Timed out")).await.unwrap();
+                return None;
+            },
+        };
+
+        interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await.unwrap();
+        prompt_reply.delete(ctx).await.unwrap();
+
+        match interaction.data.custom_id.as_str() {
+            "share" => return Some(items),
+            "copy" => {
+                let (_, copies) = items.last_mut().unwrap();
+                *copies = (*copies + 1).min(MAX_COPIES);
+            },
+            "add" => {
+                // cancelling here just goes back to the cosmetics that were already picked
+                if let Some(cosmetic) = cosmetic_select(ctx).await {
+                    add(&mut items, cosmetic);
+                }
+            },
+            "cancel" => return None,
+            id => {
+                println!("malformed component response. invalid button id \"{}\"", id);
+                return None;
+            }
+        }
+    }
+}
+
+
+impl ShareItem {
+    /// the copies that haven't been claimed yet
+    fn open_copies(&self) -> Vec<usize> {
+        Vec::from_iter(self.copies.iter().enumerate().filter(|(_, copy)| copy.outcome == ShareOutcome::Open).map(|(i, _)| i))
+    }
+
+    /// whether the user has already claimed a copy, or already had their go at one
+    fn has_claimed(&self, user_id: &str) -> bool {
+        self.copies.iter().any(|copy| copy.outcome == ShareOutcome::Claimed && copy.claimant.as_deref() == Some(user_id))
+            || self.excluded.iter().any(|id| id.to_string() == user_id)
+    }
+
+    fn describe_copies(&self) -> String {
+        // how they were picked goes in brackets, so it doesn't run into the next copy
+        let picked = |copy: usize| match self.picked[copy].strip_prefix(", ") {
+            Some(picked) => format!(" ({})", picked),
+            None => String::new(),
+        };
+
+        Vec::from_iter(self.copies.iter().enumerate().map(|(i, copy)| match (copy.outcome, &copy.claimant) {
+            (ShareOutcome::Claimed, Some(claimant)) if self.received.iter().any(|id| &id.to_string() == claimant) => format!("given to <@{}>{}", claimant, picked(i)),
+            (ShareOutcome::Claimed, Some(claimant)) => format!("claimed by <@{}>{}", claimant, picked(i)),
+            (ShareOutcome::Claimed, None) => "claimed".to_string(),
+            (ShareOutcome::Open, _) => "up for grabs".to_string(),
+            (ShareOutcome::TimedOut, _) => "no one responded".to_string(),
+            (ShareOutcome::Cancelled, _) => "cancelled".to_string(),
+        })).join(", ")
+    }
+}
+
+
+impl Share<'_> {
+
+    async fn update_status(&mut self) {
+        let items = self.items.iter().fold(String::new(), |acc, item| format!("{}\n- **{}** - {}", acc, item.cosmetic, item.describe_copies()));
+
+        self.status.edit(self.ctx, EditMessage::new().content(format!("<@{}> has found{}", self.finder, items))).await.unwrap();
+    }
+
+    /// opens a thread on the status message for sorting out the handoffs, if there isn't one yet, and adds the finder to it
+    async fn open_thread(&mut self) {
+        if !self.use_thread || self.thread.is_some() {
+            return;
+        }
+
+        let ctx = self.ctx;
+        let name: String = Vec::from_iter(self.items.iter().map(|item| item.cosmetic.as_str())).join(", ").chars().take(100).collect();
+
+        match self.status.channel_id.create_thread_from_message(ctx, self.status.id, CreateThread::new(name)).await {
+            Ok(thread) => {
+                self.thread = Some(thread.id);
+
+                if let Err(error) = thread.id.add_thread_member(ctx, self.finder).await {
+                    println!("could not add the finder to a share thread: {}", error);
+                }
+            },
+            Err(error) => {
+                // the handoffs happen in the share's channel instead
+                println!("could not create a thread for a share: {}", error);
+                self.use_thread = false;
+            },
+        }
+    }
+
+    async fn archive_thread(&self) {
+        if let Some(thread) = self.thread {
+            archive_thread(self.ctx, thread).await;
+        }
+    }
+
+    fn has_open_copies(&self) -> bool {
+        self.items.iter().any(|item| !item.open_copies().is_empty())
+    }
+
+    fn close(&mut self, item: usize, copy: usize, outcome: ShareOutcome, claimant: Option<String>) {
+        let record = &mut self.items[item].copies[copy];
+        record.close(outcome, claimant);
+        self.ctx.data().database.save_share(record);
+    }
+
+    /// gives a copy to someone, to be handed over
+    fn claim(&mut self, item: usize, copy: usize, claimant: UserId, picked: String) -> Claim {
+        self.close(item, copy, ShareOutcome::Claimed, Some(claimant.to_string()));
+        self.items[item].picked[copy] = picked.clone();
+
+        Claim { item, copy, claimant, picked }
+    }
+
+    fn reopen(&mut self, item: usize, copy: usize) {
+        let record = &mut self.items[item].copies[copy];
+        record.reopen();
+        self.ctx.data().database.save_share(record);
+    }
+
+
+    /// hands copies that were given back to the next person in line for them
+    fn pass_on(&mut self) -> Vec<Claim> {
+        let mut claims = Vec::new();
+
+        for i in 0..self.items.len() {
+            for copy in self.items[i].open_copies() {
+                let item = &mut self.items[i];
+
+                // skip anyone that got a copy some other way since they joined the line
+                while !item.next_in_line.is_empty() && item.has_claimed(&item.next_in_line[0].id.to_string()) {
+                    item.next_in_line.remove(0);
+                }
+
+                if item.next_in_line.is_empty() {
+                    break;
+                }
+
+                let claimant = item.next_in_line.remove(0);
+
+                claims.push(self.claim(i, copy, claimant.id, ", who was next in line".to_string()));
+            }
+        }
+
+        claims
+    }
+
+
+    /// pings the users that need the open copies and posts the claim message
+    async fn start_round(&self) -> Round {
+
+        let ctx = self.ctx;
+        let author_id = self.finder;
+        let mode = self.mode;
+
+        let open = Vec::from_iter(self.items.iter().map(|item| item.open_copies()));
+
+        // users that already have a copy, had their go or are snoozed don't get pinged
+        let need_records = Vec::from_iter(self.items.iter().zip(open.iter()).map(|(item, open)| {
+            if open.is_empty() {
+                return Vec::new();
+            }
+
+            Vec::from_iter(
+                ctx.data().database.pingable_need_records(&item.cosmetic).into_iter()
+                .filter(|record| !item.has_claimed(&record.user_id))
+            )
+        }));
+
+        // high priority users get pinged first and are the only ones that can claim until the priority window ends,
+        // and users that need more than one of the cosmetics only get pinged once
+        let mut high_priority: Vec<String> = Vec::new();
+        let mut everyone_else: Vec<String> = Vec::new();
+
+        for record in need_records.iter().flatten().filter(|record| record.priority == Priority::High) {
+            if !high_priority.contains(&record.user_id) {
+                high_priority.push(record.user_id.clone());
+            }
+        }
+
+        for record in need_records.iter().flatten() {
+            if !high_priority.contains(&record.user_id) && !everyone_else.contains(&record.user_id) {
+                everyone_else.push(record.user_id.clone());
+            }
+        }
+
+        let everyone_pinged = high_priority.is_empty();
+
+        // users that don't want live pings are left out
+        let (high_priority_mentioned, high_priority_dmed, high_priority_queued) = self.route(&high_priority, &need_records);
+        let (everyone_else_mentioned, everyone_else_dmed, everyone_else_queued) = self.route(&everyone_else, &need_records);
+
+        let found = format!("<@{}> has found {}", author_id, self.describe_open(&open));
+
+        let content = if need_records.iter().all(|records| records.is_empty()) {
+            format!("{} but no one needs it, you can still claim it if you need it\n\n", found)
+        } else if !everyone_pinged {
+            format!("{}\n\n{}\n\nHigh priority users have {} seconds to claim it before everyone else is pinged, if you have it but got pinged click \"Already Have It\"", found, mention(&high_priority_mentioned), PRIORITY_WINDOW.as_secs())
+        } else {
+            format!("{}\n\n{}\n\nYou can still claim it if you weren't pinged, and if you have it but got pinged click \"Already Have It\"", found, mention(&everyone_else_mentioned))
+        };
+
+        let content = format!("{}\n\n{}", content, mode.describe());
+
+        let interested: Vec<Vec<User>> = vec![Vec::new(); self.items.len()];
+
+        // claims come from the claim message, or from the buttons in people's `/inbox`.
+        // the collector is started before the message is sent so no clicks are missed
+        let message_id = Arc::new(AtomicU64::new(0));
+        let current = message_id.clone();
+        let inbox_prefix = inbox_claim_id(self.status.id.get(), "");
+
+        let interactions = ComponentInteractionCollector::new(&ctx.serenity_context().shard)
+            .filter(move |interaction| interaction.message.id.get() == current.load(Ordering::SeqCst) || interaction.data.custom_id.starts_with(&inbox_prefix))
+            .stream()
+            .boxed();
+
+        let message = self.status.channel_id.send_message(ctx, self.create_claim(&content, &open, &interested)).await.unwrap();
+        message_id.store(message.id.get(), Ordering::SeqCst);
+
+        self.dm_pinged(if everyone_pinged { &everyone_else_dmed } else { &high_priority_dmed }, &found, &message);
+        self.queue_pinged(if everyone_pinged { &everyone_else_queued } else { &high_priority_queued }, &open, &message);
+
+        let started = Instant::now();
+        let open_to_everyone = if everyone_pinged { started } else { started + PRIORITY_WINDOW };
+
+        Round {
+            message,
+            message_id,
+            interactions,
+            included: Vec::from_iter(open.iter().map(|open| !open.is_empty())),
+            open,
+            need_records,
+            interested,
+            found,
+            content,
+            everyone_pinged,
+            everyone_else_mentioned,
+            everyone_else_dmed,
+            everyone_else_queued,
+            open_to_everyone,
+            window_ends: open_to_everyone + CLAIM_WINDOW,
+            timed_out: started + self.claim_timeout,
+            ended: None,
+            _live: LiveShare::new(ctx.data().live_shares.clone(), self.status.id.get()),
+        }
+    }
+
+    /// handles a click on the claim message or an `/inbox` claim button, returning the copies that were claimed
+    async fn click(&mut self, round: &mut Round, interaction: ComponentInteraction) -> Vec<Claim> {
+
+        let ctx = self.ctx;
+        let user_id = interaction.user.id.to_string();
+
+        let mut claims = Vec::new();
+
+        // the select is used instead of the button when there's more than one cosmetic
+        let have = match &interaction.data.kind {
+            ComponentInteractionDataKind::StringSelect { values } => Vec::from_iter(values.iter().filter_map(|value| value.parse::<usize>().ok())),
+            ComponentInteractionDataKind::Button if interaction.data.custom_id == "have" => vec![0],
+            ComponentInteractionDataKind::Button => Vec::new(),
+            _ => {
+                acknowledge(ctx, &interaction).await;
+                println!("malformed component response. expected a `Button` or `StringSelect`, got {:?}", interaction.data.kind);
+                return claims;
+            },
+        };
+
+        let id = match interaction.data.custom_id.strip_prefix(&inbox_claim_id(self.status.id.get(), "")) {
+            Some(cosmetic) => match self.items.iter().position(|item| item.cosmetic == cosmetic) {
+                Some(item) => format!("claim:{}", item),
+                None => interaction.data.custom_id.clone(),
+            },
+            None => interaction.data.custom_id.clone(),
+        };
+        let id = id.as_str();
+
+        match id.split_once(':') {
+            _ if id == "cancel" => {
+                if interaction.user.id == self.finder {
+                    // cancel the share
+                    acknowledge(ctx, &interaction).await;
+                    round.ended = Some(ShareOutcome::Cancelled);
+                } else {
+                    reply_privately(ctx, &interaction, "Only the creator of the cosmetic share can cancel it").await;
+                }
+            },
+            _ if id == "have" => {
+                acknowledge(ctx, &interaction).await;
+
+                for &item in have.iter().filter(|&&item| item < self.items.len()) {
+                    ctx.data().database.remove(&self.items[item].cosmetic, &user_id);
+                    round.interested[item].retain(|user| user != &interaction.user);
+                }
+            },
+            Some(("claim", item)) => {
+                let Some(item) = item.parse::<usize>().ok().filter(|&item| item < self.items.len()) else {
+                    acknowledge(ctx, &interaction).await;
+                    println!("malformed component response. invalid button id \"{}\"", id);
+                    return claims;
+                };
+
+                let cosmetic = self.items[item].cosmetic.clone();
+
+                if self.items[item].excluded.contains(&interaction.user.id) {
+                    reply_privately(ctx, &interaction, format!("You've already had your go at **{}**", cosmetic)).await;
+                    return claims;
+                }
+
+                if self.items[item].has_claimed(&user_id) {
+                    reply_privately(ctx, &interaction, format!("You've already claimed a copy of **{}**", cosmetic)).await;
+                    return claims;
+                }
+
+                if !round.everyone_pinged && !round.is_high_priority(item, &user_id) {
+                    reply_privately(ctx, &interaction, format!("Only users that need **{}** with high priority can claim it for the first {} seconds", cosmetic, PRIORITY_WINDOW.as_secs())).await;
+                    return claims;
+                }
+
+                acknowledge(ctx, &interaction).await;
+
+                if round.open[item].is_empty() {
+                    return claims;
+                }
+
+                // after the window has ended with no one interested, it's first come first served
+                if !self.mode.uses_window() || Instant::now() >= round.window_ends {
+                    let copy = round.open[item].remove(0);
+                    claims.push(self.claim(item, copy, interaction.user.id, self.mode.describe_pick(1)));
+                } else if !round.interested[item].contains(&interaction.user) {
+                    round.interested[item].push(interaction.user);
+                }
+
+                self.edit_claim_message(round).await;
+            },
+            _ => {
+                acknowledge(ctx, &interaction).await;
+                println!("malformed component response. invalid button id \"{}\"", id);
+            }
+        }
+
+        claims
+    }
+
+    /// moves the round on once its deadline passes, returning the copies that were claimed
+    async fn deadline_passed(&mut self, round: &mut Round) -> Vec<Claim> {
+
+        let ctx = self.ctx;
+        let mode = self.mode;
+
+        let mut claims = Vec::new();
+
+        if !round.everyone_pinged {
+            // the priority window is over, so the claim message gets sent again to ping everyone else
+            round.everyone_pinged = true;
+
+            round.content = format!("{}\n\n{}\n\nHigh priority users had the first {} seconds, now anyone can claim it. If you have it but got pinged click \"Already Have It\"\n\n{}", round.found, mention(&round.everyone_else_mentioned), PRIORITY_WINDOW.as_secs(), mode.describe());
+
+            round.message.delete(ctx).await.unwrap();
+            round.message = self.status.channel_id.send_message(ctx, self.create_claim(&round.content, &round.open, &round.interested)).await.unwrap();
+            round.message_id.store(round.message.id.get(), Ordering::SeqCst);
+
+            self.dm_pinged(&round.everyone_else_dmed, &round.found, &round.message);
+            self.queue_pinged(&round.everyone_else_queued, &round.open, &round.message);
+        } else if mode.uses_window() && round.anyone_interested() {
+            // the window is over, so the interested users get the copies in order and the rest are next in line
+            for item in 0..self.items.len() {
+                let entrants = round.interested[item].len();
+
+                for claimant in claiming::rank_claimants(mode, &round.interested[item], &round.need_records[item]) {
+                    if round.open[item].is_empty() {
+                        self.items[item].next_in_line.push(claimant);
+                    } else {
+                        let copy = round.open[item].remove(0);
+                        claims.push(self.claim(item, copy, claimant.id, mode.describe_pick(entrants)));
+                    }
+                }
+
+                round.interested[item].clear();
+            }
+
+            self.edit_claim_message(round).await;
+        } else {
+            round.ended = Some(ShareOutcome::TimedOut);
+        }
+
+        claims
+    }
+
+    /// copies that were given back to everyone join the round that's going if their cosmetic is part of it
+    async fn add_reopened(&self, round: &mut Round) {
+        let mut added = false;
+
+        for (i, item) in self.items.iter().enumerate().filter(|(i, _)| round.included[*i]) {
+            for copy in item.open_copies() {
+                if !round.open[i].contains(&copy) {
+                    round.open[i].push(copy);
+                    added = true;
+                }
+            }
+        }
+
+        if added {
+            self.edit_claim_message(round).await;
+        }
+    }
+
+    /// deletes the claim message and closes the copies no one claimed
+    async fn end_round(&mut self, round: Round) {
+        round.message.delete(self.ctx).await.unwrap();
+
+        let outcome = round.ended.unwrap_or(ShareOutcome::TimedOut);
+
+        for (item, open) in round.open.into_iter().enumerate() {
+            for copy in open {
+                self.close(item, copy, outcome, None);
+            }
+        }
+    }
+
+    async fn edit_claim_message(&self, round: &mut Round) {
+        let (content, components) = self.claim_message(&round.content, &round.open, &round.interested);
+        round.message.edit(self.ctx, EditMessage::new().content(content).components(components)).await.unwrap();
+    }
+
+    fn create_claim(&self, content: &str, open: &[Vec<usize>], interested: &[Vec<User>]) -> CreateMessage {
+        let (content, components) = self.claim_message(content, open, interested);
+        let message = CreateMessage::new().content(content).components(components);
+
+        // the users are still named, but the server doesn't want them pinged
+        match self.ping_style {
+            PingStyle::Mention => message,
+            PingStyle::DirectMessage | PingStyle::Silent => message.allowed_mentions(CreateAllowedMentions::new()),
+        }
+    }
+
+    /// splits the users into the ones named in the claim message, the ones sent a dm, and the ones in their quiet hours,
+    /// going by their notification preferences and how the server wants people pinged
+    fn route(&self, users: &[String], need_records: &[Vec<NeedRecord>]) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let database = &self.ctx.data().database;
+
+        let mut mentioned = Vec::new();
+        let mut dmed = Vec::new();
+        let mut queued = Vec::new();
+
+        for user_id in users {
+            let settings = database.user_settings(user_id);
+            let prefs = &settings.notifications;
+
+            // the loudest way they want to hear about any of the cosmetics they need in the share
+            let delivery = self.items.iter().zip(need_records.iter()).filter_map(|(item, records)| {
+                let record = records.iter().find(|record| &record.user_id == user_id)?;
+                prefs.route(database.category_of(&item.cosmetic)?, record.priority)
+            }).min();
+
+            match (delivery, self.ping_style) {
+                (Some(Delivery::Ping | Delivery::DirectMessage), _) if settings.in_quiet_hours(now()) => queued.push(user_id.clone()),
+                // the server's dms still name them in the channel, the claim message just doesn't ping them
+                (Some(Delivery::Ping), PingStyle::DirectMessage) => {
+                    mentioned.push(user_id.clone());
+                    dmed.push(user_id.clone());
+                },
+                (Some(Delivery::DirectMessage), _) => dmed.push(user_id.clone()),
+                (Some(Delivery::Ping), _) => mentioned.push(user_id.clone()),
+                (Some(Delivery::Digest), _) | (None, _) => {},
+            }
+        }
+
+        (mentioned, dmed, queued)
+    }
+
+    /// saves the share for the users to be told about once their quiet hours are over
+    fn queue_pinged(&self, users: &[String], open: &[Vec<usize>], message: &Message) {
+        let description = format!("<@{}> found {}", self.finder, self.describe_open(open));
+
+        for user_id in users {
+            self.ctx.data().database.queue_share(user_id, &message.link(), &description);
+        }
+    }
+
+    /// tells the users about the share in their dms, in the background so no clicks are missed while they're sent
+    fn dm_pinged(&self, users: &[String], found: &str, message: &Message) {
+        let ctx = self.ctx.serenity_context().clone();
+        let users = users.to_vec();
+        let content = format!("{} which you need, claim it here {}", found, message.link());
+
+        tokio::spawn(async move {
+            for user_id in users {
+                let Ok(id) = user_id.parse::<u64>() else {
+                    continue;
+                };
+
+                if dm_user(&ctx, UserId::new(id), CreateMessage::new().content(content.clone())).await.is_err() {
+                    println!("could not dm user {} about a share", id);
+                }
+            }
+        });
+    }
+
+    /// lists the cosmetics that have copies up for grabs
+    fn describe_open(&self, open: &[Vec<usize>]) -> String {
+        let items = Vec::from_iter(self.items.iter().zip(open.iter()).filter(|(_, open)| !open.is_empty()).map(|(item, open)| match open.len() {
+            1 => format!("**{}**", item.cosmetic),
+            copies => format!("**{}** x{}", item.cosmetic, copies),
+        }));
+
+        items.join(", ")
+    }
+
+    /// the claim message, with a claim button for each cosmetic that still has copies left
+    fn claim_message(&self, content: &str, open: &[Vec<usize>], interested: &[Vec<User>]) -> (String, Vec<CreateActionRow>) {
+
+        let claim_label = |item: &ShareItem, open: usize| match self.mode.uses_window() {
+            true => format!("I Want {}", item.cosmetic),
+            false => format!("Claim {} ({} left)", item.cosmetic, open),
+        };
+
+        let buttons = Vec::from_iter(self.items.iter().zip(open.iter()).enumerate().filter(|(_, (_, open))| !open.is_empty()).map(|(i, (item, open))| {
+            CreateButton::new(format!("claim:{}", i)).style(ButtonStyle::Success).label(claim_label(item, open.len()))
+        }));
+
+        let mut components = Vec::from_iter(buttons.chunks(5).map(|buttons| CreateActionRow::Buttons(buttons.to_vec())));
+
+        if self.items.len() == 1 {
+            components.push(CreateActionRow::Buttons(vec![
+                CreateButton::new("cancel").style(ButtonStyle::Danger).label("Cancel"),
+                CreateButton::new("have").style(ButtonStyle::Primary).label("Already Have It"),
+            ]));
+        } else {
+            let options = Vec::from_iter(self.items.iter().enumerate().map(|(i, item)| CreateSelectMenuOption::new(&item.cosmetic, i.to_string())));
+
+            components.push(CreateActionRow::SelectMenu(
+                CreateSelectMenu::new("have", CreateSelectMenuKind::String { options })
+                    .placeholder("Already Have It")
+                    .min_values(1)
+                    .max_values(self.items.len() as u8)
+            ));
+            components.push(CreateActionRow::Buttons(vec![
+                CreateButton::new("cancel").style(ButtonStyle::Danger).label("Cancel"),
+            ]));
+        }
+
+        let counts = self.items.iter().zip(interested.iter()).filter(|(_, users)| !users.is_empty()).fold(String::new(), |acc, (item, users)| {
+            format!("{}\n**{}** interested in **{}** so far", acc, users.len(), item.cosmetic)
+        });
+
+        (format!("{}\n{}", content, counts), components)
+    }
+
+
+    /// starts handing a claimed copy over
+    fn handoff(&self, claim: Claim) -> impl Future<Output = (Claim, HandoffOutcome)> + Send + 'static {
+        let handoff = Handoff {
+            ctx: self.ctx.serenity_context().clone(),
+            finder: self.finder,
+            channel: self.thread.unwrap_or(self.status.channel_id),
+            thread: self.thread,
+            handoffs: self.handoffs.clone(),
+            cosmetic: self.items[claim.item].cosmetic.clone(),
+            note: self.note.clone(),
+            claim,
+        };
+
+        handoff.run()
+    }
+
+    fn finish_handoff(&mut self, claim: Claim, outcome: HandoffOutcome) {
+        let database = &self.ctx.data().database;
+        let user_id = claim.claimant.to_string();
+        let item = &mut self.items[claim.item];
+
+        match outcome {
+            HandoffOutcome::Received => {
+                // they don't need it anymore so they won't get pinged for it again
+                database.remove(&item.cosmetic, &user_id);
+                database.add_owned(&item.cosmetic, &user_id);
+                item.received.push(claim.claimant);
+            },
+            HandoffOutcome::TimedOut => {},
+            HandoffOutcome::Failed => {
+                item.excluded.push(claim.claimant);
+                self.reopen(claim.item, claim.copy);
+            },
+            HandoffOutcome::Released => {
+                item.excluded.push(claim.claimant);
+
+                // everyone that still needs it gets pinged again rather than just the people that were interested last time
+                item.next_in_line.clear();
+                self.reopen(claim.item, claim.copy);
+            },
+        }
+    }
+}
+
+
+impl Round {
+    fn is_high_priority(&self, item: usize, user_id: &str) -> bool {
+        self.need_records[item].iter().any(|record| record.user_id == user_id && record.priority == Priority::High)
+    }
+
+    fn anyone_interested(&self) -> bool {
+        self.interested.iter().any(|users| !users.is_empty())
+    }
+
+    fn is_over(&self) -> bool {
+        self.ended.is_some() || self.open.iter().all(|open| open.is_empty())
+    }
+
+    /// when the round next has to move on if no one clicks anything
+    fn deadline(&self, mode: ClaimMode) -> Instant {
+        // once someone is interested the winners are picked when the window ends
+        let mut deadline = if mode.uses_window() && self.anyone_interested() { self.window_ends } else { self.timed_out };
+
+        if !self.everyone_pinged {
+            deadline = deadline.min(self.open_to_everyone);
+        }
+
+        deadline
+    }
+}
+
+
+impl Handoff {
+    /// waits for the finder or claimant to say whether the copy was handed over
+    async fn run(self) -> (Claim, HandoffOutcome) {
+
+        let ctx = &self.ctx;
+        let claim = &self.claim;
+        let claimant = claim.claimant;
+        let cosmetic = &self.cosmetic;
+
+        self.handoffs.fetch_add(1, Ordering::SeqCst);
+
+        let note = match self.send_note().await {
+            Some(true) => format!("\n\n<@{}> check your dms for the finder's handoff note", claimant),
+            Some(false) => format!("\n\n<@{}> the finder left a handoff note but it couldn't be sent to your dms, ask them for it", claimant),
+            None => String::new(),
+        };
+
+        let mut message = self.channel.send_message(ctx, CreateMessage::new()
+            .content(format!("<@{}> found **{}** which has been claimed by <@{}>{}\n\nClick \"Received\" once it's been handed over, or \"Handoff Failed\" if it didn't work out and it'll go to the next person. If the claimant doesn't show up or doesn't want it anymore, releasing it pings everyone that needs it again{}", self.finder, cosmetic, claimant, claim.picked, note))
+            .components(vec![
+                CreateActionRow::Buttons(vec![
+                    CreateButton::new("received").style(ButtonStyle::Success).label("Received"),
+                    CreateButton::new("failed").style(ButtonStyle::Danger).label("Handoff Failed"),
+                    CreateButton::new("noshow").style(ButtonStyle::Secondary).label("Didn't Show / Release"),
+                    CreateButton::new("release").style(ButtonStyle::Secondary).label("Release Claim"),
+                ]),
+            ])
+        ).await.unwrap();
+
+        if let Some(thread) = self.thread {
+            if let Err(error) = thread.add_thread_member(ctx, claimant).await {
+                println!("could not add a claimant to a share thread: {}", error);
+            }
+        }
+
+        let mut interactions = ComponentInteractionCollector::new(&ctx.shard)
+            .message_id(message.id)
+            .timeout(HANDOFF_TIMEOUT)
+            .stream();
+
+        let outcome = loop {
+            let Some(interaction) = interactions.next().await else {
+                break HandoffOutcome::TimedOut;
+            };
+
+            let is_finder = interaction.user.id == self.finder;
+            let is_claimant = interaction.user.id == claimant;
+
+            match interaction.data.custom_id.as_str() {
+                "received" | "failed" if !is_finder && !is_claimant => {
+                    reply_privately(ctx, &interaction, "Only the finder and the claimant can confirm the handoff").await;
+                },
+                "noshow" if !is_finder => {
+                    reply_privately(ctx, &interaction, "Only the finder can release a claim when the claimant doesn't show up").await;
+                },
+                "release" if !is_claimant => {
+                    reply_privately(ctx, &interaction, "Only the claimant can release their claim").await;
+                },
+                "received" => {
+                    acknowledge(ctx, &interaction).await;
+                    break HandoffOutcome::Received;
+                },
+                "failed" => {
+                    acknowledge(ctx, &interaction).await;
+                    break HandoffOutcome::Failed;
+                },
+                "noshow" | "release" => {
+                    acknowledge(ctx, &interaction).await;
+                    break HandoffOutcome::Released;
+                },
+                id => {
+                    acknowledge(ctx, &interaction).await;
+                    println!("malformed component response. invalid button id \"{}\"", id);
+                }
+            }
+        };
+
+        let content = match outcome {
+            HandoffOutcome::Received => format!("<@{}> gave **{}** to <@{}>{}", self.finder, cosmetic, claimant, claim.picked),
+            HandoffOutcome::TimedOut => format!("<@{}> found **{}** which has been claimed by <@{}>{}\n\nMake sure to use the `/dontneed` command later so you don't get pinged again", self.finder, cosmetic, claimant, claim.picked),
+            HandoffOutcome::Failed => format!("The handoff of **{}** from <@{}> to <@{}> didn't work out, so it goes to the next person", cosmetic, self.finder, claimant),
+            HandoffOutcome::Released => format!("<@{}> released their claim on **{}** so it's up for grabs again", claimant, cosmetic),
+        };
+
+        message.edit(ctx, EditMessage::new().content(content).components(Vec::new())).await.unwrap();
+
+        // the thread is archived while there's nothing to hand over, the next handoff message unarchives it
+        if self.handoffs.fetch_sub(1, Ordering::SeqCst) == 1 {
+            if let Some(thread) = self.thread {
+                archive_thread(ctx, thread).await;
+            }
+        }
+
+        (self.claim, outcome)
+    }
+
+    /// sends the finder's private note to the claimant, returning whether they got it
+    async fn send_note(&self) -> Option<bool> {
+        let note = self.note.as_ref()?;
+
+        let content = format!("<@{}> left a note for whoever gets **{}**\n\n{}", self.finder, self.cosmetic, note);
+
+        let sent = dm_user(&self.ctx, self.claim.claimant, CreateMessage::new().content(content)).await;
+
+        if sent.is_err() {
+            println!("could not dm user {} the handoff note", self.claim.claimant);
+        }
+
+        Some(sent.is_ok())
+    }
+}
+
+
+impl LiveShare {
+    fn new(live_shares: Arc<std::sync::Mutex<HashSet<u64>>>, id: u64) -> Self {
+        live_shares.lock().unwrap().insert(id);
+        LiveShare { live_shares, id }
+    }
+}
+
+impl Drop for LiveShare {
+    fn drop(&mut self) {
+        self.live_shares.lock().unwrap().remove(&self.id);
+    }
+}
+
+
+async fn archive_thread(ctx: impl CacheHttp, thread: ChannelId) {
+    if let Err(error) = thread.edit_thread(ctx, EditThread::new().archived(true)).await {
+        println!("could not archive a share thread: {}", error);
+    }
+}
+
+/// mentions each of the users
+fn mention(users: &[String]) -> String {
+    users.iter().fold(String::new(), |acc, user| format!("{} <@{}>", acc, user))
+}
@@ -0,0 +1,174 @@
+use poise::CreateReply;
+
+use crate::user_settings::UserSettings;
+use crate::{format_duration, now, Context, Error};
+
+
+const DAY: u64 = 60 * 60 * 24;
+
+/// anything longer should be a vacation
+const MAX_SNOOZE: u64 = DAY * 30;
+
+const MAX_VACATION_DAYS: u64 = 365;
+
+
+/// reads a length of time like `45m`, `12h`, `3d` or `1d12h`, in seconds
+pub fn parse_duration(duration: &str) -> Option<u64> {
+    let mut seconds = 0;
+    let mut amount = String::new();
+
+    for c in duration.trim().to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            amount.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => DAY,
+            'w' => DAY * 7,
+            _ => return None,
+        };
+
+        seconds += amount.parse::<u64>().ok()?.checked_mul(unit)?;
+        amount.clear();
+    }
+
+    // a number on its own is in hours
+    if !amount.is_empty() {
+        seconds += amount.parse::<u64>().ok()?.checked_mul(60 * 60)?;
+    }
+
+    (seconds > 0).then_some(seconds)
+}
+
+
+fn describe(settings: &UserSettings) -> String {
+    let time = now();
+
+    let snooze = match settings.snoozed_until.filter(|&until| until > time) {
+        Some(until) => format!("Your pings are snoozed until <t:{}:f>, that's {} from now", until, format_duration(until - time)),
+        None => "Your pings aren't snoozed".to_string(),
+    };
+
+    let vacation = match settings.vacation_until.filter(|&until| until > time) {
+        Some(until) => format!("\nYou're on vacation until <t:{}:D>, so you won't be pinged, offered trades or sent digests until then", until),
+        None => String::new(),
+    };
+
+    format!("{}{}", snooze, vacation)
+}
+
+
+#[poise::command(slash_command)]
+pub async fn snooze(
+    ctx: Context<'_>,
+    #[description = "How long to stop being pinged for, like 45m, 12h or 3d"]
+    duration: Option<String>,
+    #[description = "Start getting pinged again"]
+    off: Option<bool>,
+) -> Result<(), Error> {
+
+
+    let user_id = ctx.author().id.to_string();
+
+    let mut settings = ctx.data().database.user_settings(&user_id);
+
+    let reply = |content: String| CreateReply::default().content(content).ephemeral(true);
+
+    if let Some(duration) = duration {
+        let Some(seconds) = parse_duration(&duration).filter(|&seconds| seconds <= MAX_SNOOZE) else {
+            ctx.send(reply(format!("\"{}\" isn't a length of time up to {}, use something like `45m`, `12h` or `3d`, or `/vacation` for longer breaks", duration, format_duration(MAX_SNOOZE)))).await.unwrap();
+            return Ok(());
+        };
+
+        settings.snoozed_until = Some(now() + seconds);
+    }
+
+    if off == Some(true) {
+        settings.snoozed_until = None;
+    }
+
+    ctx.data().database.set_user_settings(&user_id, &settings);
+
+    ctx.send(reply(describe(&settings))).await.unwrap();
+
+
+    Ok(())
+}
+
+
+#[poise::command(slash_command)]
+pub async fn vacation(
+    ctx: Context<'_>,
+    #[description = "How many days you'll be away for, your needs are kept for when you're back"]
+    #[min = 1]
+    #[max = 365]
+    days: Option<u64>,
+    #[description = "You're back early"]
+    off: Option<bool>,
+) -> Result<(), Error> {
+
+
+    let user_id = ctx.author().id.to_string();
+
+    let mut settings = ctx.data().database.user_settings(&user_id);
+
+    if let Some(days) = days {
+        settings.vacation_until = Some(now() + days.min(MAX_VACATION_DAYS) * DAY);
+    }
+
+    if off == Some(true) {
+        settings.vacation_until = None;
+    }
+
+    ctx.data().database.set_user_settings(&user_id, &settings);
+
+    ctx.send(
+        CreateReply::default()
+            .content(describe(&settings))
+            .ephemeral(true)
+    ).await.unwrap();
+
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_units() {
+        assert_eq!(parse_duration("45m"), Some(45 * 60));
+        assert_eq!(parse_duration("12h"), Some(12 * 60 * 60));
+        assert_eq!(parse_duration("3d"), Some(3 * DAY));
+        assert_eq!(parse_duration("2w"), Some(14 * DAY));
+    }
+
+    #[test]
+    fn parses_combined_units() {
+        assert_eq!(parse_duration("1d12h"), Some(DAY + 12 * 60 * 60));
+        assert_eq!(parse_duration("1h 30m"), Some(90 * 60));
+        assert_eq!(parse_duration(" 2H "), Some(2 * 60 * 60));
+    }
+
+    #[test]
+    fn bare_numbers_are_hours() {
+        assert_eq!(parse_duration("8"), Some(8 * 60 * 60));
+        assert_eq!(parse_duration("1d6"), Some(DAY + 6 * 60 * 60));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("0"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("3x"), None);
+        assert_eq!(parse_duration("-1h"), None);
+        assert_eq!(parse_duration("99999999999999999999h"), None);
+        assert_eq!(parse_duration("9999999999999999w"), None);
+    }
+}
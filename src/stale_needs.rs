@@ -0,0 +1,287 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use poise::CreateReply;
+use serenity::all::{ButtonStyle, ComponentInteraction, ComponentInteractionCollector, ComponentInteractionDataKind, MessageId, UserId};
+use serenity::builder::{CreateActionRow, CreateButton, CreateMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditMessage};
+use serenity::prelude::*;
+
+use crate::{acknowledge, dm_user, now, Database, Error};
+
+
+const DAY: u64 = 60 * 60 * 24;
+
+/// how long users can go without using the bot before they're asked if they still need things, unless they've set their own
+pub const DEFAULT_CHECK_IN_DAYS: u64 = 60;
+
+/// how long users have to answer before their needs are archived
+const RESPONSE_WINDOW: u64 = DAY * 7;
+
+/// the most needs a select menu can show
+const MAX_REVIEW_OPTIONS: usize = 25;
+
+
+/// dms everyone that hasn't been around for a while asking if they still need their cosmetics
+pub async fn send_check_ins(ctx: &Context, database: &Arc<Database>) {
+
+    let time = now();
+
+    for (user_id, last_confirmed) in database.last_confirmed_needs() {
+        let mut settings = database.user_settings(&user_id);
+
+        // people on vacation said they'd be away
+        if settings.stale_asked_at.is_some() || settings.on_vacation(time) {
+            continue;
+        }
+
+        let Some(check_in_after) = settings.check_in_days().map(|days| days * DAY) else {
+            continue;
+        };
+
+        // the clock starts from when they were first seen if the bot has never recorded them doing anything
+        let Some(last_active) = database.last_active(&user_id).max(last_confirmed) else {
+            database.set_last_active(&user_id, time);
+            continue;
+        };
+
+        if time < last_active + check_in_after {
+            continue;
+        }
+
+        let Ok(id) = user_id.parse::<u64>() else {
+            continue;
+        };
+
+        let needs = database.needed_by(&user_id).len();
+
+        let message = CreateMessage::new()
+            .content(format!("You haven't used the bot in {}, do you still need your **{}** cosmetics?\n\nIf you don't answer in {} they'll be archived so you stop getting pinged, and you can bring them back with `/unarchive`", crate::format_duration(time - last_active), needs, crate::format_duration(RESPONSE_WINDOW)))
+            .components(vec![
+                CreateActionRow::Buttons(vec![
+                    CreateButton::new("stale:keep").style(ButtonStyle::Success).label("Keep All"),
+                    CreateButton::new("stale:review").style(ButtonStyle::Primary).label("Review"),
+                    CreateButton::new("stale:clear").style(ButtonStyle::Danger).label("Clear All"),
+                ]),
+            ]);
+
+        // the response window still applies if they can't be dm'd
+        let message_id = match dm_user(ctx, UserId::new(id), message).await {
+            Ok(message) => Some(message.id),
+            Err(_) => {
+                println!("could not dm user {} about their stale needs", id);
+                None
+            },
+        };
+
+        settings.stale_asked_at = Some(time);
+        settings.stale_message = message_id.map(MessageId::get);
+        database.set_user_settings(&user_id, &settings);
+
+        tokio::spawn(await_response(ctx.clone(), database.clone(), UserId::new(id), time, message_id));
+    }
+}
+
+/// starts waiting again for the answers to check ins that were sent before the bot restarted
+pub fn resume(ctx: &Context, database: &Arc<Database>) {
+    for user_id in database.users_with_settings() {
+        let settings = database.user_settings(&user_id);
+
+        let Some(asked_at) = settings.stale_asked_at else {
+            continue;
+        };
+
+        let Ok(id) = user_id.parse::<u64>() else {
+            continue;
+        };
+
+        tokio::spawn(await_response(ctx.clone(), database.clone(), UserId::new(id), asked_at, settings.stale_message.map(MessageId::new)));
+    }
+}
+
+
+/// handles the buttons on a check in until the user decides, or archives their needs if they don't
+async fn await_response(ctx: Context, database: Arc<Database>, user_id: UserId, asked_at: u64, message_id: Option<MessageId>) {
+
+    let user = user_id.to_string();
+    let deadline = asked_at + RESPONSE_WINDOW;
+
+    // there's nothing to answer if the check in couldn't be sent
+    let Some(message_id) = message_id else {
+        tokio::time::sleep(Duration::from_secs(deadline.saturating_sub(now()))).await;
+        archive(&ctx, &database, user_id, asked_at).await;
+        return;
+    };
+
+    loop {
+        // only this check in, so buttons left on an older one don't answer it
+        let interaction = ComponentInteractionCollector::new(&ctx.shard)
+            .author_id(user_id)
+            .message_id(message_id)
+            .filter(|interaction| interaction.data.custom_id.starts_with("stale:"))
+            .timeout(Duration::from_secs(deadline.saturating_sub(now())))
+            .await;
+
+        let Some(interaction) = interaction else {
+            archive(&ctx, &database, user_id, asked_at).await;
+            return;
+        };
+
+        acknowledge(&ctx, &interaction).await;
+
+        let content = match (interaction.data.custom_id.as_str(), &interaction.data.kind) {
+            ("stale:keep", _) => {
+                let needs = database.needed_by(&user);
+
+                for cosmetic in needs.iter() {
+                    database.confirm(cosmetic, &user);
+                }
+
+                format!("Kept all **{}** of your needs", needs.len())
+            },
+            ("stale:review", _) => {
+                review(&ctx, &database, &interaction).await;
+                continue;
+            },
+            ("stale:remove", ComponentInteractionDataKind::StringSelect { values }) => {
+                for cosmetic in values.iter().filter(|cosmetic| database.is_cosmetic(cosmetic)) {
+                    database.remove(cosmetic, &user);
+                }
+
+                // the ones they didn't pick still count as needed
+                for cosmetic in database.needed_by(&user) {
+                    database.confirm(&cosmetic, &user);
+                }
+
+                format!("Removed **{}** of your needs and kept the rest", values.len())
+            },
+            ("stale:clear", _) => {
+                let needs = database.needed_by(&user);
+
+                for cosmetic in needs.iter() {
+                    database.remove(cosmetic, &user);
+                }
+
+                format!("Cleared all **{}** of your needs", needs.len())
+            },
+            (id, kind) => {
+                println!("malformed component response. invalid id \"{}\" for {:?}", id, kind);
+                continue;
+            },
+        };
+
+        edit(&ctx, &interaction, EditMessage::new().content(content).components(Vec::new())).await;
+
+        let mut settings = database.user_settings(&user);
+        settings.stale_asked_at = None;
+        settings.stale_message = None;
+        database.set_user_settings(&user, &settings);
+        database.set_last_active(&user, now());
+
+        return;
+    }
+}
+
+/// swaps the check in's buttons for a menu of the needs they've gone the longest without confirming
+async fn review(ctx: &Context, database: &Database, interaction: &ComponentInteraction) {
+
+    let user = interaction.user.id.to_string();
+
+    let mut needs = Vec::from_iter(
+        database.needed_by(&user).into_iter()
+        .filter_map(|cosmetic| Some((database.need_record(&user, &cosmetic)?, cosmetic)))
+    );
+
+    needs.sort_by_key(|(record, _)| record.confirmed_at.or(record.created_at));
+
+    let content = match needs.len() > MAX_REVIEW_OPTIONS {
+        true => format!("Pick the ones you don't need anymore, the rest will be kept. These are the {} you've gone the longest without confirming, you can go through the rest with `/whatdoineed`", MAX_REVIEW_OPTIONS),
+        false => "Pick the ones you don't need anymore, the rest will be kept".to_string(),
+    };
+
+    let options = Vec::from_iter(needs.iter().take(MAX_REVIEW_OPTIONS).map(|(record, cosmetic)| {
+        CreateSelectMenuOption::new(cosmetic, cosmetic).description(record.describe_age())
+    }));
+
+    let mut components = Vec::new();
+
+    if !options.is_empty() {
+        let count = options.len() as u8;
+
+        components.push(CreateActionRow::SelectMenu(
+            CreateSelectMenu::new("stale:remove", CreateSelectMenuKind::String { options })
+                .placeholder("Don't need anymore")
+                .min_values(1)
+                .max_values(count)
+        ));
+    }
+
+    components.push(CreateActionRow::Buttons(vec![
+        CreateButton::new("stale:keep").style(ButtonStyle::Success).label("Keep All"),
+    ]));
+
+    edit(ctx, interaction, EditMessage::new().content(content).components(components)).await;
+}
+
+/// moves the user's needs out of the way if they haven't been around since they were asked about them
+async fn archive(ctx: &Context, database: &Database, user_id: UserId, asked_at: u64) {
+
+    let user = user_id.to_string();
+
+    let mut settings = database.user_settings(&user);
+
+    // they answered some other way, like a restart losing the buttons
+    if settings.stale_asked_at != Some(asked_at) {
+        return;
+    }
+
+    settings.stale_asked_at = None;
+    settings.stale_message = None;
+    database.set_user_settings(&user, &settings);
+
+    // using the bot since counts as still needing them
+    if database.last_active(&user).is_some_and(|last_active| last_active > asked_at) {
+        return;
+    }
+
+    let archived = database.archive_needs(&user);
+
+    if archived == 0 {
+        return;
+    }
+
+    let content = format!("You didn't answer, so your **{}** needs have been archived and you won't be pinged about them. Use `/unarchive` to bring them back", archived);
+
+    if dm_user(ctx, user_id, CreateMessage::new().content(content)).await.is_err() {
+        println!("could not dm user {} about their archived needs", user_id);
+    }
+}
+
+async fn edit(ctx: &Context, interaction: &ComponentInteraction, message: EditMessage) {
+    if interaction.message.channel_id.edit_message(ctx, interaction.message.id, message).await.is_err() {
+        println!("could not edit the stale needs check in for user {}", interaction.user.id);
+    }
+}
+
+
+#[poise::command(slash_command)]
+pub async fn unarchive(
+    ctx: crate::Context<'_>,
+) -> Result<(), Error> {
+
+
+    let restored = ctx.data().database.restore_needs(&ctx.author().id.to_string());
+
+    let content = match restored.len() {
+        0 => "You don't have any archived needs".to_string(),
+        count => format!("Brought back **{}** of your needs", count),
+    };
+
+    ctx.send(
+        CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+    ).await.unwrap();
+
+
+    Ok(())
+}
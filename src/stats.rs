@@ -0,0 +1,212 @@
+
+use std::collections::{HashMap, HashSet};
+
+use serenity::all::{GuildId, UserId};
+use serenity::futures::StreamExt;
+use serenity::prelude::*;
+
+use crate::shares::ShareOutcome;
+use crate::{CosmeticCategory, Database, MEMBER_LOOKUPS, UNKNOWN_MEMBER};
+
+
+/// how many of the most needed cosmetics are listed for each category
+const TOP_PER_CATEGORY: usize = 3;
+
+const TOP_FINDERS: usize = 5;
+
+
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq, Eq)]
+pub enum StatsScope {
+    #[name = "This Server"]
+    Server,
+    #[name = "Global"]
+    Global,
+}
+
+
+pub struct Stats {
+    /// every cosmetic that is needed by someone with how many users need it, most needed first
+    pub needs_by_category: Vec<(CosmeticCategory, Vec<(String, usize)>)>,
+    pub total_needs: usize,
+    /// users that need at least one cosmetic
+    pub active_users: usize,
+    /// how long the middle need has been needed for in seconds, only counting needs with a known age
+    pub median_need_age: Option<u64>,
+
+    pub shares_created: usize,
+    /// shares can have several copies, which each end their own way
+    pub copies_shared: usize,
+    pub copies_claimed: usize,
+    pub copies_timed_out: usize,
+    pub copies_cancelled: usize,
+    /// in seconds
+    pub median_time_to_claim: Option<u64>,
+    /// finders with how many shares they've created, most first
+    pub top_finders: Vec<(String, usize)>,
+    /// when each copy was shared and how it ended
+    pub share_history: Vec<(u64, ShareOutcome)>,
+}
+
+
+/// aggregates the needs and share history, only counting users in the guild if one is given
+pub async fn collect(database: &Database, guild: Option<(&Context, GuildId)>) -> Stats {
+
+    let guild_id = guild.map(|(_, guild_id)| guild_id);
+
+    // the needs are all read first, so everyone's membership can be looked up together
+    let records = Vec::from_iter(CosmeticCategory::ALL.iter().map(|&category| {
+        (category, Vec::from_iter(database.cosmetics_in_category(category).map(|cosmetic| (cosmetic, database.need_records(cosmetic)))))
+    }));
+
+    let users = HashSet::<String>::from_iter(records.iter().flat_map(|(_, cosmetics)| cosmetics.iter().flat_map(|(_, records)| records.iter().map(|record| record.user_id.clone()))));
+
+    // membership is looked up once per user, a few at a time
+    let in_guild: HashMap<String, bool> = match guild {
+        None => HashMap::from_iter(users.into_iter().map(|user_id| (user_id, true))),
+        Some((ctx, guild_id)) => serenity::futures::stream::iter(users)
+            .map(|user_id| async move {
+                let member = is_member(ctx, guild_id, &user_id).await;
+                (user_id, member)
+            })
+            .buffered(MEMBER_LOOKUPS)
+            .collect()
+            .await,
+    };
+
+    let mut needs_by_category = Vec::new();
+    let mut total_needs = 0;
+    let mut need_ages = Vec::new();
+
+    for (category, cosmetics) in records.iter() {
+        let mut counts = Vec::new();
+
+        for (cosmetic, records) in cosmetics {
+            let mut count = 0;
+
+            for record in records.iter().filter(|record| in_guild.get(&record.user_id).copied().unwrap_or(false)) {
+                count += 1;
+                need_ages.extend(record.age());
+            }
+
+            if count > 0 {
+                counts.push(((*cosmetic).clone(), count));
+                total_needs += count;
+            }
+        }
+
+        counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+        needs_by_category.push((*category, counts));
+    }
+
+    let active_users = in_guild.values().filter(|&&member| member).count();
+
+    need_ages.sort();
+
+
+    let shares = Vec::from_iter(
+        database.shares().into_iter()
+        .filter(|share| guild_id.is_none() || share.guild_id == guild_id.map(|id| id.get()))
+    );
+
+    let count = |outcome| shares.iter().filter(|share| share.outcome == outcome).count();
+
+    let mut claim_times = Vec::from_iter(
+        shares.iter()
+        .filter(|share| share.outcome == ShareOutcome::Claimed)
+        .filter_map(|share| Some(share.closed_at?.saturating_sub(share.created_at)))
+    );
+    claim_times.sort();
+
+    // every share has a first copy, so counting those counts each share once
+    let first_copies = Vec::from_iter(shares.iter().filter(|share| share.copy == 0));
+
+    let mut finders: HashMap<&String, usize> = HashMap::new();
+    for share in first_copies.iter().filter(|share| share.finder != crate::FORGOTTEN_USER) {
+        *finders.entry(&share.finder).or_default() += 1;
+    }
+
+    let mut top_finders = Vec::from_iter(finders.into_iter().map(|(finder, count)| (finder.clone(), count)));
+    top_finders.sort_by(|(_, a), (_, b)| b.cmp(a));
+    top_finders.truncate(TOP_FINDERS);
+
+
+    Stats {
+        needs_by_category,
+        total_needs,
+        active_users,
+        median_need_age: need_ages.get(need_ages.len() / 2).copied(),
+
+        shares_created: first_copies.len(),
+        copies_shared: shares.len(),
+        copies_claimed: count(ShareOutcome::Claimed),
+        copies_timed_out: count(ShareOutcome::TimedOut),
+        copies_cancelled: count(ShareOutcome::Cancelled),
+        median_time_to_claim: claim_times.get(claim_times.len() / 2).copied(),
+        top_finders,
+        share_history: Vec::from_iter(shares.iter().map(|share| (share.created_at, share.outcome))),
+    }
+}
+
+/// whether the user is in the guild, from the cache when they're there.
+/// only discord saying they aren't counts as not being a member, so users aren't dropped from the stats when discord is having problems
+async fn is_member(ctx: &Context, guild_id: GuildId, user_id: &str) -> bool {
+    let Ok(user_id) = user_id.parse::<u64>() else {
+        return false;
+    };
+    let user_id = UserId::new(user_id);
+
+    if ctx.cache.guild(guild_id).is_some_and(|guild| guild.members.contains_key(&user_id)) {
+        return true;
+    }
+
+    match guild_id.member(ctx, user_id).await {
+        Ok(_) => true,
+        Err(serenity::Error::Http(serenity::all::HttpError::UnsuccessfulRequest(response))) if response.error.code == UNKNOWN_MEMBER => false,
+        Err(_) => true,
+    }
+}
+
+
+impl Stats {
+    pub fn describe(&self) -> String {
+
+        let most_needed = self.needs_by_category.iter().fold(String::new(), |acc, (category, counts)| {
+            let top = counts.iter().take(TOP_PER_CATEGORY)
+                .fold(String::new(), |acc, (cosmetic, count)| format!("{}\n**{}** ({})", acc, cosmetic, count));
+
+            if top.is_empty() {
+                format!("{}\n\n__{}__\nnothing needed", acc, category)
+            } else {
+                format!("{}\n\n__{}__{}", acc, category, top)
+            }
+        });
+
+        let median = match self.median_time_to_claim {
+            Some(seconds) => format!("{}m {}s", seconds / 60, seconds % 60),
+            None => "no claims yet".to_string(),
+        };
+
+        let need_age = match self.median_need_age {
+            Some(seconds) => crate::format_duration(seconds),
+            None => "unknown".to_string(),
+        };
+
+        let finders = self.top_finders.iter().enumerate()
+            .fold(String::new(), |acc, (i, (finder, count))| format!("{}\n{}. <@{}> ({})", acc, i + 1, finder, count));
+
+        format!(
+            "**Most needed**{}\n\n**{}** needs from **{}** users\nMedian need age: {}\n\n**Shares**\n{} created with {} copies between them\nCopies: {} claimed, {} timed out, {} cancelled\nMedian time to claim: {}\n\n**Top finders**{}",
+            most_needed,
+            self.total_needs,
+            self.active_users,
+            need_age,
+            self.shares_created,
+            self.copies_shared,
+            self.copies_claimed,
+            self.copies_timed_out,
+            self.copies_cancelled,
+            median,
+            if finders.is_empty() { "\nno shares yet".to_string() } else { finders },
+        )
+    }
+}
@@ -0,0 +1,369 @@
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serenity::all::{ButtonStyle, ComponentInteractionDataKind, Message, UserId};
+use serenity::builder::{CreateActionRow, CreateButton, CreateMessage};
+use serenity::futures::stream::{FuturesUnordered, StreamExt};
+use serenity::prelude::*;
+
+use crate::{acknowledge, dm_user, now, Database};
+
+
+/// longest chain of users that will be proposed as one trade
+const MAX_CYCLE_LENGTH: usize = 4;
+
+/// most trades that get searched for and listed at once
+const MAX_TRADES: usize = 10;
+
+const TRADE_RESPONSE_TIMEOUT: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// how long a trade that has been proposed isn't offered again, in seconds
+const PROPOSAL_EXPIRY: u64 = 60 * 60 * 24 * 30;
+
+
+/// one user giving a cosmetic they have spare to a user that needs it
+pub struct TradeStep {
+    pub giver: String,
+    pub receiver: String,
+    pub cosmetic: String,
+}
+
+/// a cycle of users where everyone gives the next user something they need,
+/// a cycle of two is a plain swap between two users
+pub struct Trade {
+    pub steps: Vec<TradeStep>,
+}
+
+impl Trade {
+    /// identifies the trade regardless of which user the cycle was found from
+    pub fn key(&self) -> String {
+        let mut steps = Vec::from_iter(self.steps.iter().map(|step| format!("{}>{}:{}", step.giver, step.receiver, step.cosmetic)));
+        steps.sort();
+        steps.join(",")
+    }
+
+    pub fn users(&self) -> impl Iterator<Item = &String> + '_ {
+        self.steps.iter().map(|step| &step.giver)
+    }
+
+    pub fn gives(&self, user_id: &str) -> Option<&TradeStep> {
+        self.steps.iter().find(|step| step.giver == user_id)
+    }
+
+    pub fn receives(&self, user_id: &str) -> Option<&TradeStep> {
+        self.steps.iter().find(|step| step.receiver == user_id)
+    }
+
+    /// describes the trade from the point of view of one of it's users
+    pub fn describe_for(&self, user_id: &str) -> String {
+        let (Some(gives), Some(receives)) = (self.gives(user_id), self.receives(user_id)) else {
+            return String::new();
+        };
+
+        let mut description = format!("You give **{}** to <@{}> and get **{}** from <@{}>", gives.cosmetic, gives.receiver, receives.cosmetic, receives.giver);
+
+        if self.steps.len() > 2 {
+            let others = self.steps.iter()
+                .filter(|step| step.giver != user_id && step.receiver != user_id)
+                .fold(String::new(), |acc, step| format!("{}\n<@{}> gives **{}** to <@{}>", acc, step.giver, step.cosmetic, step.receiver));
+
+            description = format!("{}{}", description, others);
+        }
+
+        description
+    }
+}
+
+
+/// who each user can give to and with what
+pub type TradeGraph = HashMap<String, Vec<(String, String)>>;
+
+/// finds cycles of users going through `user_id` where every user has a spare of something the next user needs
+pub fn find_trades(database: &Database, user_id: &str) -> Vec<Trade> {
+    find_cycles(&trade_graph(database), user_id)
+}
+
+/// the trades in `graph` going through `user_id`, shortest first
+pub fn find_cycles(graph: &TradeGraph, user_id: &str) -> Vec<Trade> {
+    let mut trades = Vec::new();
+    let mut path = vec![user_id.to_string()];
+    let mut steps = Vec::new();
+
+    search(graph, &mut path, &mut steps, &mut trades);
+
+    trades.sort_by_key(|trade| trade.steps.len());
+    trades.truncate(MAX_TRADES);
+    trades
+}
+
+fn search(
+    graph: &TradeGraph,
+    path: &mut Vec<String>,
+    steps: &mut Vec<(String, String)>,
+    trades: &mut Vec<Trade>,
+) {
+    if trades.len() >= MAX_TRADES * 4 {
+        return;
+    }
+
+    let giver = path.last().unwrap().clone();
+
+    let Some(receivers) = graph.get(&giver) else {
+        return;
+    };
+
+    for (receiver, cosmetic) in receivers {
+
+        if receiver == &path[0] && path.len() >= 2 {
+            let mut trade_steps = Vec::new();
+
+            for (i, (giver, cosmetic)) in steps.iter().chain(std::iter::once(&(giver.clone(), cosmetic.clone()))).enumerate() {
+                trade_steps.push(TradeStep {
+                    giver: giver.clone(),
+                    receiver: path.get(i + 1).unwrap_or(&path[0]).clone(),
+                    cosmetic: cosmetic.clone(),
+                });
+            }
+
+            trades.push(Trade { steps: trade_steps });
+            continue;
+        }
+
+        if path.contains(receiver) || path.len() >= MAX_CYCLE_LENGTH {
+            continue;
+        }
+
+        path.push(receiver.clone());
+        steps.push((giver.clone(), cosmetic.clone()));
+        search(graph, path, steps, trades);
+        steps.pop();
+        path.pop();
+    }
+}
+
+/// every user with spares, and every other user that needs one of them with one cosmetic they could be given.
+/// reads each cosmetic's files once, so the search doesn't have to touch the database.
+///
+/// snoozed users aren't offered trades until they're back
+fn trade_graph(database: &Database) -> TradeGraph {
+    let mut graph: TradeGraph = HashMap::new();
+    let snoozed = database.snoozed_users(now());
+
+    for cosmetic in database.all_cosmetics() {
+        let givers = database.who_has_spare(cosmetic);
+
+        if givers.is_empty() {
+            continue;
+        }
+
+        let needers = Vec::from_iter(
+            database.need_records(cosmetic).into_iter()
+            .map(|record| record.user_id)
+            .filter(|needer| !snoozed.contains(needer))
+        );
+
+        for giver in givers {
+            let receivers = graph.entry(giver.clone()).or_default();
+
+            for needer in needers.iter() {
+                if needer != &giver && !receivers.iter().any(|(receiver, _)| receiver == needer) {
+                    receivers.push((needer.clone(), cosmetic.clone()));
+                }
+            }
+        }
+    }
+
+    graph
+}
+
+
+/// looks for trades the user is now part of and hasn't already been offered, and dms everyone involved
+pub async fn propose_trades(ctx: &Context, data: &crate::Data, user_id: &str) {
+
+    let trades = find_trades(&data.database, user_id);
+
+    let mut proposed = data.proposed_trades.lock().await;
+
+    // declined trades can be offered again once they're old enough that things might have changed
+    let time = now();
+    proposed.retain(|_, proposed_at| *proposed_at + PROPOSAL_EXPIRY > time);
+
+    for trade in trades {
+        // each trade only gets proposed once, even if it gets declined
+        if proposed.contains_key(&trade.key()) {
+            continue;
+        }
+
+        proposed.insert(trade.key(), time);
+        tokio::spawn(run_proposal(ctx.clone(), trade));
+    }
+
+    data.database.set_proposed_trades(&proposed);
+}
+
+async fn run_proposal(ctx: Context, trade: Trade) {
+
+    let mut prompts = Vec::new();
+
+    // everyone gets asked at once, so one decline can call the trade off for everyone straight away
+    let mut accepted = true;
+    for user_id in trade.users() {
+        let Ok(user_id) = user_id.parse::<u64>() else {
+            accepted = false;
+            break;
+        };
+
+        match ask_user(&ctx, UserId::new(user_id), trade.describe_for(&user_id.to_string())).await {
+            Some(prompt) => prompts.push(prompt),
+            None => {
+                accepted = false;
+                break;
+            }
+        }
+    }
+
+    if accepted {
+        let mut responses = FuturesUnordered::from_iter(prompts.iter().map(|prompt| {
+            let collector = prompt.await_component_interaction(&ctx.shard).timeout(TRADE_RESPONSE_TIMEOUT);
+            async move { collector.await }
+        }));
+
+        while let Some(interaction) = responses.next().await {
+            let Some(interaction) = interaction else {
+                accepted = false;
+                break;
+            };
+
+            acknowledge(&ctx, &interaction).await;
+
+            let ComponentInteractionDataKind::Button = interaction.data.kind else {
+                println!("malformed component response. expected a `Button`, got {:?}", interaction.data.kind);
+                accepted = false;
+                break;
+            };
+
+            if interaction.data.custom_id != "accept" {
+                accepted = false;
+                break;
+            }
+        }
+    }
+
+    for prompt in prompts {
+        let _ = prompt.delete(&ctx).await;
+    }
+
+    let content = if accepted {
+        "Everyone accepted the trade, message each other to organise it\n\nMake sure to use `/dontneed` and `/nospare` once you've traded"
+    } else {
+        "Someone declined or didn't respond, so the trade is off"
+    };
+
+    for user_id in trade.users() {
+        let Ok(user_id) = user_id.parse::<u64>() else {
+            continue;
+        };
+
+        let description = trade.describe_for(&user_id.to_string());
+
+        if dm_user(&ctx, UserId::new(user_id), CreateMessage::new().content(format!("{}\n\n{}", description, content))).await.is_err() {
+            println!("could not dm user {} about a trade", user_id);
+        }
+    }
+}
+
+/// dms a user a trade with buttons to accept or decline it
+async fn ask_user(ctx: &Context, user_id: UserId, description: String) -> Option<Message> {
+
+    let message = dm_user(ctx, user_id, CreateMessage::new()
+        .content(format!("There's a trade you can make\n\n{}", description))
+        .components(vec![
+            CreateActionRow::Buttons(vec![
+                CreateButton::new("accept").style(ButtonStyle::Success).label("Accept"),
+                CreateButton::new("decline").style(ButtonStyle::Danger).label("Decline"),
+            ]),
+        ])
+    ).await;
+
+    if message.is_err() {
+        println!("could not dm user {} about a trade", user_id);
+    }
+
+    message.ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &str, &str)]) -> TradeGraph {
+        let mut graph: TradeGraph = HashMap::new();
+
+        for (giver, receiver, cosmetic) in edges {
+            graph.entry(giver.to_string()).or_default().push((receiver.to_string(), cosmetic.to_string()));
+        }
+
+        graph
+    }
+
+    #[test]
+    fn finds_swaps() {
+        let trades = find_cycles(&graph(&[("a", "b", "Hat"), ("b", "a", "Top")]), "a");
+
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].key(), "a>b:Hat,b>a:Top");
+        assert_eq!(trades[0].gives("b").unwrap().cosmetic, "Top");
+        assert_eq!(trades[0].receives("a").unwrap().giver, "b");
+    }
+
+    #[test]
+    fn finds_longer_cycles() {
+        let trades = find_cycles(&graph(&[("a", "b", "Hat"), ("b", "c", "Top"), ("c", "a", "Belt")]), "a");
+
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].steps.len(), 3);
+        assert_eq!(trades[0].key(), "a>b:Hat,b>c:Top,c>a:Belt");
+    }
+
+    #[test]
+    fn same_trade_from_every_user() {
+        let graph = graph(&[("a", "b", "Hat"), ("b", "c", "Top"), ("c", "a", "Belt")]);
+
+        let key = find_cycles(&graph, "a")[0].key();
+        assert_eq!(find_cycles(&graph, "b")[0].key(), key);
+        assert_eq!(find_cycles(&graph, "c")[0].key(), key);
+    }
+
+    #[test]
+    fn ignores_chains_that_dont_come_back() {
+        assert!(find_cycles(&graph(&[("a", "b", "Hat"), ("b", "c", "Top")]), "a").is_empty());
+        assert!(find_cycles(&graph(&[]), "a").is_empty());
+    }
+
+    #[test]
+    fn ignores_cycles_not_through_the_user() {
+        let trades = find_cycles(&graph(&[("a", "b", "Hat"), ("b", "c", "Top"), ("c", "b", "Belt")]), "a");
+
+        assert!(trades.is_empty());
+    }
+
+    #[test]
+    fn limits_cycle_length() {
+        let four = [("a", "b", "Hat"), ("b", "c", "Top"), ("c", "d", "Vest"), ("d", "a", "Belt")];
+        let five = [("a", "b", "Hat"), ("b", "c", "Top"), ("c", "d", "Vest"), ("d", "e", "Belt"), ("e", "a", "Bottom")];
+
+        assert_eq!(find_cycles(&graph(&four), "a").len(), 1);
+        assert!(find_cycles(&graph(&five), "a").is_empty());
+    }
+
+    #[test]
+    fn shortest_trades_first() {
+        let trades = find_cycles(&graph(&[("a", "b", "Hat"), ("b", "c", "Top"), ("c", "a", "Belt"), ("b", "a", "Vest")]), "a");
+
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].steps.len(), 2);
+        assert_eq!(trades[1].steps.len(), 3);
+    }
+}
@@ -0,0 +1,134 @@
+
+use std::collections::HashMap;
+
+use crate::digest::DigestFrequency;
+use crate::notifications::NotificationPrefs;
+use crate::quiet_hours::{QuietHours, Timezone};
+use crate::stale_needs::DEFAULT_CHECK_IN_DAYS;
+
+
+/// options each user can set for themselves, stored as `key value` lines in `database/users/<user id>.txt`
+#[derive(Default)]
+pub struct UserSettings {
+    /// lets other users in the same server see this user's name in `/whoneeds`
+    pub visible: bool,
+    pub notifications: NotificationPrefs,
+    pub timezone: Option<Timezone>,
+    pub quiet_hours: Option<QuietHours>,
+    pub digest: DigestFrequency,
+    /// when the last digest was sent, or when they turned it on
+    pub last_digest: Option<u64>,
+    /// how many times in a row their digest couldn't be sent, and when it last failed
+    pub digest_failures: u64,
+    pub digest_failed_at: Option<u64>,
+    /// no pings or trade offers until then
+    pub snoozed_until: Option<u64>,
+    /// a longer snooze that also holds back their digest until then
+    pub vacation_until: Option<u64>,
+    /// how many days they can go without using the bot before being asked if they still need things, 0 never asks
+    pub check_in: Option<u64>,
+    /// when they were asked, until they answer or their needs are archived
+    pub stale_asked_at: Option<u64>,
+    /// the check in they were sent, if it could be sent
+    pub stale_message: Option<u64>,
+}
+
+impl UserSettings {
+    pub fn from_key_values(values: &HashMap<String, String>) -> Self {
+        let mut settings = UserSettings::default();
+
+        if let Some(visible) = values.get("visible") {
+            settings.visible = visible == "true";
+        }
+
+        settings.notifications = NotificationPrefs::from_key_values(values);
+        settings.timezone = Timezone::from_key_values(values);
+        settings.quiet_hours = QuietHours::from_key_values(values);
+        settings.digest = values.get("digest").and_then(|id| DigestFrequency::from_id(id)).unwrap_or_default();
+        settings.last_digest = values.get("last_digest").and_then(|time| time.parse().ok());
+        settings.digest_failures = values.get("digest_failures").and_then(|count| count.parse().ok()).unwrap_or(0);
+        settings.digest_failed_at = values.get("digest_failed_at").and_then(|time| time.parse().ok());
+        settings.snoozed_until = values.get("snoozed_until").and_then(|time| time.parse().ok());
+        settings.vacation_until = values.get("vacation_until").and_then(|time| time.parse().ok());
+        settings.check_in = values.get("check_in").and_then(|days| days.parse().ok());
+        settings.stale_asked_at = values.get("stale_asked_at").and_then(|time| time.parse().ok());
+        settings.stale_message = values.get("stale_message").and_then(|id| id.parse().ok());
+
+        settings
+    }
+
+    pub fn to_key_values(&self) -> Vec<(&'static str, String)> {
+        let mut values = vec![
+            ("visible", self.visible.to_string()),
+        ];
+
+        values.extend(self.notifications.to_key_values());
+
+        if let Some(timezone) = self.timezone {
+            values.extend(timezone.to_key_values());
+        }
+
+        if let Some(quiet_hours) = self.quiet_hours {
+            values.extend(quiet_hours.to_key_values());
+        }
+
+        values.push(("digest", self.digest.to_id().to_string()));
+
+        if let Some(last_digest) = self.last_digest {
+            values.push(("last_digest", last_digest.to_string()));
+        }
+
+        if let Some(digest_failed_at) = self.digest_failed_at {
+            values.push(("digest_failures", self.digest_failures.to_string()));
+            values.push(("digest_failed_at", digest_failed_at.to_string()));
+        }
+
+        if let Some(snoozed_until) = self.snoozed_until {
+            values.push(("snoozed_until", snoozed_until.to_string()));
+        }
+
+        if let Some(vacation_until) = self.vacation_until {
+            values.push(("vacation_until", vacation_until.to_string()));
+        }
+
+        if let Some(check_in) = self.check_in {
+            values.push(("check_in", check_in.to_string()));
+        }
+
+        if let Some(stale_asked_at) = self.stale_asked_at {
+            values.push(("stale_asked_at", stale_asked_at.to_string()));
+        }
+
+        if let Some(stale_message) = self.stale_message {
+            values.push(("stale_message", stale_message.to_string()));
+        }
+
+        values
+    }
+
+    /// snoozes and vacations end on their own once their time has passed
+    pub fn on_vacation(&self, time: u64) -> bool {
+        self.vacation_until.is_some_and(|until| until > time)
+    }
+
+    pub fn snoozed(&self, time: u64) -> bool {
+        self.snoozed_until.is_some_and(|until| until > time) || self.on_vacation(time)
+    }
+
+    /// how many days they can go without using the bot before being asked about their needs, or `None` if they never are
+    pub fn check_in_days(&self) -> Option<u64> {
+        match self.check_in {
+            Some(0) => None,
+            Some(days) => Some(days),
+            None => Some(DEFAULT_CHECK_IN_DAYS),
+        }
+    }
+
+    /// quiet hours only work once the user has said what timezone they're in
+    pub fn in_quiet_hours(&self, time: u64) -> bool {
+        match (&self.quiet_hours, self.timezone) {
+            (Some(quiet_hours), Some(timezone)) => quiet_hours.contains(time, timezone.utc_offset(time)),
+            _ => false,
+        }
+    }
+}